@@ -5,7 +5,16 @@ pub const MEMORY_END: usize = 0x88000000;
 pub const PAGE_SIZE: usize = 0x1000;
 pub const PAGE_SIZE_BITS: usize = 0xc;
 pub const MAX_SYSCALL_NUM: usize = 500;
+/// the stride of a task grows by `BIG_STRIDE / priority` each time it is scheduled;
+/// it must not exceed `i64::MAX` for strides to be compared across wraparound
+pub const BIG_STRIDE: u64 = u32::MAX as u64;
+/// priority of a task that has not called set_priority
+pub const DEFAULT_PRIORITY: usize = 16;
 
+/// end of the lower half of the Sv39 address space, which user mappings must stay in
+pub const USER_SPACE_END: usize = 1 << 38;
+/// lowest address mmap picks when the caller leaves the choice to the kernel
+pub const MMAP_BASE: usize = 0x1000_0000;
 pub const TRAMPOLINE: usize = usize::MAX - PAGE_SIZE + 1;
 pub const TRAP_CONTEXT: usize = TRAMPOLINE - PAGE_SIZE;
 /// Return (bottom, top) of a kernel stack in kernel space.
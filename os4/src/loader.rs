@@ -1,3 +1,6 @@
+use alloc::vec::Vec;
+use lazy_static::*;
+
 pub fn get_num_app() -> usize {
     extern "C" {
         fn _num_app();
@@ -20,3 +23,35 @@ pub fn get_app_data(app_id: usize) -> &'static [u8] {
         )
     }
 }
+
+lazy_static! {
+    /// names of the apps, in the same order as their data
+    static ref APP_NAMES: Vec<&'static str> = {
+        let num_app = get_num_app();
+        extern "C" {
+            fn _app_names();
+        }
+        let mut start = _app_names as usize as *const u8;
+        let mut v = Vec::new();
+        unsafe {
+            for _ in 0..num_app {
+                let mut end = start;
+                while end.read_volatile() != b'\0' {
+                    end = end.add(1);
+                }
+                let slice = core::slice::from_raw_parts(start, end as usize - start as usize);
+                let str = core::str::from_utf8(slice).unwrap();
+                v.push(str);
+                start = end.add(1);
+            }
+        }
+        v
+    };
+}
+
+/// Find the ELF data of the app called `name`.
+pub fn get_app_data_by_name(name: &str) -> Option<&'static [u8]> {
+    (0..get_num_app())
+        .find(|&i| APP_NAMES[i] == name)
+        .map(get_app_data)
+}
@@ -46,6 +46,8 @@ pub fn rust_main() -> ! {
     mm::init();
     println!("[kernel] back to world!");
     mm::remap_test();
+    #[cfg(feature = "self-tests")]
+    self_tests();
     trap::init();
     //trap::enable_interrupt();
     trap::enable_timer_interrupt();
@@ -53,3 +55,32 @@ pub fn rust_main() -> ! {
     task::run_first_task();
     panic!("Unreachable in rust_main!");
 }
+
+/// Self-tests of the kernel, built with `FEATURES=self-tests`.
+#[cfg(feature = "self-tests")]
+fn self_tests() {
+    mm::identical_area_test();
+    mm::va_rounding_test();
+    mm::frame_alloc_contiguous_test();
+    mm::frame_alloc_batch_test();
+    mm::frame_zeroing_test();
+    mm::page_copy_test();
+    mm::copy_kernel_to_user_test();
+    mm::translated_str_test();
+    mm::cow_test();
+    mm::lazy_area_test();
+    mm::oom_rollback_test();
+    mm::unmap_leak_test();
+    mm::find_pte_create_test();
+    mm::map_range_test();
+    mm::port_flags_test();
+    mm::iter_mappings_test();
+    mm::map_huge_test();
+    mm::pte_debug_test();
+    task::pid_allocator_test();
+    task::mapped_ranges_test();
+    task::task_pids_test();
+    task::stride_test();
+    timer::monotonic_clock_test();
+    timer::uptime_clock_test();
+}
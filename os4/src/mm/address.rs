@@ -4,6 +4,16 @@ use super::PageTableEntry;
 use crate::config::{PAGE_SIZE, PAGE_SIZE_BITS};
 use core::fmt::{self, Debug, Formatter};
 
+/// width of a virtual address in Sv39
+const VA_WIDTH_SV39: usize = 39;
+/// width of a physical page number in Sv39
+const PPN_WIDTH_SV39: usize = 44;
+/// one past the largest physical page number
+const PPN_END: usize = 1 << PPN_WIDTH_SV39;
+/// one past the largest virtual page number; virtual addresses are not
+/// truncated to 39 bits, so the pages of the high half lie right below it
+const VPN_END: usize = 1 << (usize::BITS as usize - PAGE_SIZE_BITS);
+
 /// physical address
 #[derive(Copy, Clone, Ord, PartialOrd, Eq, PartialEq)]
 pub struct PhysAddr(pub usize);
@@ -88,12 +98,17 @@ impl From<VirtPageNum> for usize {
     }
 }
 
+/// A region `[start_va, end_va)` covers the pages from `start_va.floor()` up to
+/// but not including `end_va.ceil()`, so a partial last page is included.
 impl VirtAddr {
+    /// The page containing this address.
     pub fn floor(&self) -> VirtPageNum {
         VirtPageNum(self.0 / PAGE_SIZE)
     }
+    /// The first page starting at or above this address.
     pub fn ceil(&self) -> VirtPageNum {
-        VirtPageNum((self.0 - 1 + PAGE_SIZE) / PAGE_SIZE)
+        // no `+ PAGE_SIZE - 1`, which underflows at 0 and overflows near the top
+        VirtPageNum(self.0 / PAGE_SIZE + (self.page_offset() != 0) as usize)
     }
     pub fn page_offset(&self) -> usize {
         self.0 & (PAGE_SIZE - 1)
@@ -101,6 +116,12 @@ impl VirtAddr {
     pub fn aligned(&self) -> bool {
         self.page_offset() == 0
     }
+    /// Whether bits 63..39 are all copies of bit 38, as Sv39 requires. Other
+    /// addresses would alias the canonical one with the same low 39 bits.
+    pub fn is_canonical(&self) -> bool {
+        let high = (self.0 as isize) >> (VA_WIDTH_SV39 - 1);
+        high == 0 || high == -1
+    }
 }
 impl From<VirtAddr> for VirtPageNum {
     fn from(v: VirtAddr) -> Self {
@@ -126,6 +147,12 @@ impl PhysAddr {
     pub fn aligned(&self) -> bool {
         self.page_offset() == 0
     }
+    pub fn get_ref<T>(&self) -> &'static T {
+        unsafe { (self.0 as *const T).as_ref().unwrap() }
+    }
+    pub fn get_mut<T>(&self) -> &'static mut T {
+        unsafe { (self.0 as *mut T).as_mut().unwrap() }
+    }
 }
 impl From<PhysAddr> for PhysPageNum {
     fn from(v: PhysAddr) -> Self {
@@ -160,18 +187,45 @@ impl PhysPageNum {
         let pa: PhysAddr = (*self).into();
         unsafe { core::slice::from_raw_parts_mut(pa.0 as *mut u8, 4096) }
     }
+    /// The bytes `start..end` of the page, or `None` if the range is reversed
+    /// or runs past the end of the page.
+    pub fn get_bytes_range(&self, start: usize, end: usize) -> Option<&'static mut [u8]> {
+        if start > end || end > PAGE_SIZE {
+            return None;
+        }
+        Some(&mut self.get_bytes_array()[start..end])
+    }
     pub fn get_mut<T>(&self) -> &'static mut T {
         let pa: PhysAddr = (*self).into();
         unsafe { (pa.0 as *mut T).as_mut().unwrap() }
     }
+    /// Fill the whole page with zeros.
+    pub fn zero(&self) {
+        self.get_bytes_array().fill(0);
+    }
+    /// Overwrite the whole page with the contents of the page `src`.
+    pub fn copy_page_from(&self, src: PhysPageNum) {
+        if *self != src {
+            self.get_bytes_array().copy_from_slice(src.get_bytes_array());
+        }
+    }
 }
 
 pub trait StepByOne {
     fn step(&mut self);
 }
+/// Page numbers step at most to one past the largest one, which is the exclusive
+/// end of a range reaching the top of the space, so loops up to it terminate.
 impl StepByOne for VirtPageNum {
     fn step(&mut self) {
-        self.0 += 1;
+        debug_assert!(self.0 < VPN_END, "{:?} steps past the top of the address space", self);
+        self.0 = self.0.saturating_add(1).min(VPN_END);
+    }
+}
+impl StepByOne for PhysPageNum {
+    fn step(&mut self) {
+        debug_assert!(self.0 < PPN_END, "{:?} steps past the top of physical memory", self);
+        self.0 = self.0.saturating_add(1).min(PPN_END);
     }
 }
 
@@ -243,3 +297,35 @@ where
 
 /// a simple range structure for virtual page number
 pub type VPNRange = SimpleRange<VirtPageNum>;
+
+#[allow(unused)]
+/// rounding of virtual addresses to pages and stepping through them, including both
+/// ends of the address space
+pub fn va_rounding_test() {
+    assert_eq!(VirtAddr(0).floor(), VirtPageNum(0));
+    assert_eq!(VirtAddr(0).ceil(), VirtPageNum(0));
+    assert_eq!(VirtAddr(1).ceil(), VirtPageNum(1));
+    assert_eq!(VirtAddr(PAGE_SIZE).floor(), VirtPageNum(1));
+    assert_eq!(VirtAddr(PAGE_SIZE).ceil(), VirtPageNum(1));
+    // a region of PAGE_SIZE + 1 bytes covers two pages
+    let start = VirtAddr(0x10000000);
+    let end = VirtAddr(0x10000000 + PAGE_SIZE + 1);
+    assert!(start.aligned() && !end.aligned());
+    assert_eq!(end.ceil().0 - start.floor().0, 2);
+    assert_eq!(VirtAddr(usize::MAX).ceil(), VirtPageNum(usize::MAX / PAGE_SIZE + 1));
+    assert!(VirtAddr(0).is_canonical() && VirtAddr((1 << 38) - 1).is_canonical());
+    assert!(VirtAddr(usize::MAX).is_canonical() && VirtAddr(!((1 << 38) - 1)).is_canonical());
+    assert!(!VirtAddr(1 << 38).is_canonical());
+    assert!(!VirtAddr(0x10000000 | 1 << 40).is_canonical());
+    assert!(!VirtAddr(1 << 63).is_canonical());
+    // ranges reaching the top of the space still end
+    let mut vpn = VirtPageNum(VPN_END - 1);
+    vpn.step();
+    assert_eq!(vpn, VirtPageNum(VPN_END));
+    assert_eq!(VPNRange::new(VirtPageNum(VPN_END - 2), VirtPageNum(VPN_END)).into_iter().count(), 2);
+    assert_eq!(VirtAddr(usize::MAX).floor(), VirtPageNum(VPN_END - 1));
+    let mut ppn = PhysPageNum(PPN_END - 1);
+    ppn.step();
+    assert_eq!(ppn, PhysPageNum(PPN_END));
+    info!("va_rounding_test passed!");
+}
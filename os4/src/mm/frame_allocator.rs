@@ -2,8 +2,9 @@
 //! controls all the frames in the operating system.
 
 use super::{PhysAddr, PhysPageNum};
-use crate::config::MEMORY_END;
+use crate::config::{MEMORY_END, PAGE_SIZE};
 use crate::sync::UPSafeCell;
+use crate::timer::get_time;
 use alloc::vec::Vec;
 use core::fmt::{self, Debug, Formatter};
 use lazy_static::*;
@@ -15,11 +16,8 @@ pub struct FrameTracker {
 
 impl FrameTracker {
     pub fn new(ppn: PhysPageNum) -> Self {
-        // page cleaning
-        let bytes_array = ppn.get_bytes_array();
-        for i in bytes_array {
-            *i = 0;
-        }
+        // page cleaning, so that no data of a previous owner leaks into a new mapping
+        ppn.zero();
         Self { ppn }
     }
 }
@@ -39,11 +37,18 @@ impl Drop for FrameTracker {
 trait FrameAllocator {
     fn new() -> Self;
     fn alloc(&mut self) -> Option<PhysPageNum>;
+    fn alloc_contiguous(&mut self, count: usize) -> Option<PhysPageNum>;
+    fn alloc_batch(&mut self, count: usize) -> Option<Vec<PhysPageNum>>;
     fn dealloc(&mut self, ppn: PhysPageNum);
+    fn allocated(&self) -> usize;
+    fn free(&self) -> usize;
+    fn total(&self) -> usize;
 }
 
 /// an implementation for frame allocator
 pub struct StackFrameAllocator {
+    /// first frame under management
+    start: usize,
     current: usize,
     end: usize,
     recycled: Vec<usize>,
@@ -51,6 +56,7 @@ pub struct StackFrameAllocator {
 
 impl StackFrameAllocator {
     pub fn init(&mut self, l: PhysPageNum, r: PhysPageNum) {
+        self.start = l.0;
         self.current = l.0;
         self.end = r.0;
     }
@@ -58,6 +64,7 @@ impl StackFrameAllocator {
 impl FrameAllocator for StackFrameAllocator {
     fn new() -> Self {
         Self {
+            start: 0,
             current: 0,
             end: 0,
             recycled: Vec::new(),
@@ -73,15 +80,54 @@ impl FrameAllocator for StackFrameAllocator {
             Some((self.current - 1).into())
         }
     }
+    /// Only frames that have never been allocated are handed out here, since
+    /// recycled frames are rarely contiguous. Returns the first ppn of the run.
+    fn alloc_contiguous(&mut self, count: usize) -> Option<PhysPageNum> {
+        if self.end - self.current < count {
+            None
+        } else {
+            self.current += count;
+            Some((self.current - count).into())
+        }
+    }
+    /// Recycled frames are taken first, then never allocated ones. Either all
+    /// `count` frames are handed out or none.
+    fn alloc_batch(&mut self, count: usize) -> Option<Vec<PhysPageNum>> {
+        if self.free() < count {
+            return None;
+        }
+        let from_recycled = count.min(self.recycled.len());
+        let mut ppns: Vec<PhysPageNum> = self
+            .recycled
+            .drain(self.recycled.len() - from_recycled..)
+            .map(PhysPageNum::from)
+            .collect();
+        let rest = count - from_recycled;
+        ppns.extend((self.current..self.current + rest).map(PhysPageNum::from));
+        self.current += rest;
+        Some(ppns)
+    }
     fn dealloc(&mut self, ppn: PhysPageNum) {
         let ppn = ppn.0;
         // validity check
-        if ppn >= self.current || self.recycled.iter().any(|v| *v == ppn) {
+        if ppn >= self.current {
             panic!("Frame ppn={:#x} has not been allocated!", ppn);
         }
+        if self.recycled.iter().any(|v| *v == ppn) {
+            panic!("Frame ppn={:#x} is freed twice!", ppn);
+        }
         // recycle
         self.recycled.push(ppn);
     }
+    fn allocated(&self) -> usize {
+        self.current - self.recycled.len()
+    }
+    fn free(&self) -> usize {
+        self.end - self.current + self.recycled.len()
+    }
+    fn total(&self) -> usize {
+        self.end - self.start
+    }
 }
 
 type FrameAllocatorImpl = StackFrameAllocator;
@@ -103,7 +149,7 @@ pub fn init_frame_allocator() {
     );
 }
 
-/// allocate a frame
+/// allocate a frame, which is always zeroed so that no stale data leaks to its new owner
 pub fn frame_alloc() -> Option<FrameTracker> {
     FRAME_ALLOCATOR
         .exclusive_access()
@@ -111,6 +157,65 @@ pub fn frame_alloc() -> Option<FrameTracker> {
         .map(FrameTracker::new)
 }
 
+/// allocate `count` physically contiguous frames
+///
+/// The frames come from the part of memory that has never been allocated, so
+/// this can fail even when enough recycled frames are free.
+pub fn frame_alloc_contiguous(count: usize) -> Option<Vec<FrameTracker>> {
+    let start = FRAME_ALLOCATOR
+        .exclusive_access()
+        .alloc_contiguous(count)?;
+    Some(
+        (start.0..start.0 + count)
+            .map(|ppn| FrameTracker::new(ppn.into()))
+            .collect(),
+    )
+}
+
+/// allocate `count` zeroed frames at once, which need not be contiguous
+///
+/// The allocator is locked only once, and nothing is allocated if fewer than
+/// `count` frames are free.
+pub fn frame_alloc_batch(count: usize) -> Option<Vec<FrameTracker>> {
+    let ppns = FRAME_ALLOCATOR.exclusive_access().alloc_batch(count)?;
+    Some(ppns.into_iter().map(FrameTracker::new).collect())
+}
+
+/// number of frames currently in use, counted from the start of the allocator
+/// so only differences between two calls are meaningful
+pub fn frame_allocated_count() -> usize {
+    FRAME_ALLOCATOR.exclusive_access().allocated()
+}
+
+/// number of frames currently available for allocation
+pub fn free_frame_count() -> usize {
+    FRAME_ALLOCATOR.exclusive_access().free()
+}
+
+/// Usage of physical frames, as reported by `sys_frame_stats`.
+#[repr(C)]
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct FrameStats {
+    /// frames managed by the allocator
+    pub total: usize,
+    /// frames handed out and not yet returned
+    pub used: usize,
+    /// frames available for allocation
+    pub free: usize,
+}
+
+/// a consistent snapshot of the frame usage, `used + free == total`
+pub fn frame_stats() -> FrameStats {
+    let allocator = FRAME_ALLOCATOR.exclusive_access();
+    let total = allocator.total();
+    let free = allocator.free();
+    FrameStats {
+        total,
+        used: total - free,
+        free,
+    }
+}
+
 /// deallocate a frame
 fn frame_dealloc(ppn: PhysPageNum) {
     FRAME_ALLOCATOR.exclusive_access().dealloc(ppn);
@@ -134,3 +239,95 @@ pub fn frame_allocator_test() {
     drop(v);
     info!("frame_allocator_test passed!");
 }
+
+#[allow(unused)]
+/// a simple test for contiguous frame allocation
+pub fn frame_alloc_contiguous_test() {
+    let frames = frame_alloc_contiguous(4).unwrap();
+    for pair in frames.windows(2) {
+        assert_eq!(pair[0].ppn.0 + 1, pair[1].ppn.0);
+    }
+    drop(frames);
+    info!("frame_alloc_contiguous_test passed!");
+}
+
+#[allow(unused)]
+/// a recycled frame must come back zeroed
+pub fn frame_zeroing_test() {
+    let frame = frame_alloc().unwrap();
+    let ppn = frame.ppn;
+    ppn.get_bytes_array().fill(0xa5);
+    drop(frame);
+    // recycled frames are reused in LIFO order
+    let frame = frame_alloc().unwrap();
+    assert_eq!(frame.ppn, ppn);
+    assert!(frame.ppn.get_bytes_array().iter().all(|byte| *byte == 0));
+    info!("frame_zeroing_test passed!");
+}
+
+#[allow(unused)]
+/// copying a page duplicates all its bytes, and zeroing clears them again;
+/// byte ranges are checked against the page size
+pub fn page_copy_test() {
+    let src = frame_alloc().unwrap();
+    let dst = frame_alloc().unwrap();
+    for (i, byte) in src.ppn.get_bytes_array().iter_mut().enumerate() {
+        *byte = (i * 7 + 3) as u8;
+    }
+    dst.ppn.copy_page_from(src.ppn);
+    assert_eq!(dst.ppn.get_bytes_array(), src.ppn.get_bytes_array());
+    // copying a page onto itself leaves it unchanged
+    dst.ppn.copy_page_from(dst.ppn);
+    assert_eq!(dst.ppn.get_bytes_array(), src.ppn.get_bytes_array());
+    dst.ppn.zero();
+    assert!(dst.ppn.get_bytes_array().iter().all(|byte| *byte == 0));
+    assert_eq!(src.ppn.get_bytes_array()[1], 10);
+    // partial ranges stay inside the page
+    assert_eq!(src.ppn.get_bytes_range(1, 3).unwrap(), &[10, 17]);
+    assert_eq!(src.ppn.get_bytes_range(PAGE_SIZE, PAGE_SIZE).unwrap().len(), 0);
+    assert!(src.ppn.get_bytes_range(PAGE_SIZE - 1, PAGE_SIZE + 1).is_none());
+    assert!(src.ppn.get_bytes_range(3, 1).is_none());
+    info!("page_copy_test passed!");
+}
+
+#[allow(unused)]
+/// a batch is all or nothing, and its frames are distinct and zeroed; the
+/// frame statistics add up
+///
+/// The time of allocating 1024 frames one by one and as a batch is printed for
+/// comparison; it depends on the machine, so no numbers are asserted.
+pub fn frame_alloc_batch_test() {
+    let free = free_frame_count();
+    assert!(frame_alloc_batch(free + 1).is_none());
+    assert_eq!(free_frame_count(), free);
+    // mix recycled frames into the batch
+    let recycled: Vec<FrameTracker> = (0..3).map(|_| frame_alloc().unwrap()).collect();
+    drop(recycled);
+    let start = get_time();
+    let batch = frame_alloc_batch(1024).unwrap();
+    let batch_ticks = get_time() - start;
+    assert_eq!(free_frame_count(), free - 1024);
+    let mut ppns: Vec<usize> = batch.iter().map(|frame| frame.ppn.0).collect();
+    ppns.sort_unstable();
+    ppns.dedup();
+    assert_eq!(ppns.len(), 1024);
+    assert!(batch.iter().all(|frame| frame.ppn.get_bytes_array().iter().all(|byte| *byte == 0)));
+    drop(batch);
+    assert_eq!(free_frame_count(), free);
+    let start = get_time();
+    let single: Vec<FrameTracker> = (0..1024).map(|_| frame_alloc().unwrap()).collect();
+    let single_ticks = get_time() - start;
+    drop(single);
+    let stats = frame_stats();
+    assert_eq!(stats.used + stats.free, stats.total);
+    assert_eq!(stats.free, free_frame_count());
+    let frame = frame_alloc().unwrap();
+    assert_eq!(frame_stats().used, stats.used + 1);
+    drop(frame);
+    assert_eq!(frame_stats(), stats);
+    info!(
+        "1024 frames: {} ticks one by one, {} ticks as a batch",
+        single_ticks, batch_ticks
+    );
+    info!("frame_alloc_batch_test passed!");
+}
@@ -1,12 +1,14 @@
 //! Implementation of [`MapArea`] and [`MemorySet`].
 
-use super::{frame_alloc, FrameTracker};
-use super::{PTEFlags, PageTable, PageTableEntry};
+use super::{frame_alloc, frame_allocated_count, free_frame_count, FrameTracker};
+use super::{check_user_ptr, copy_kernel_to_user, copy_type_from_user, copy_type_into_user, copy_user_to_kernel};
+use super::{flush_tlb_all, translated_ref, translated_refmut, translated_str, PTEFlags, PageTable, PageTableEntry, UserPtrReader};
 use super::{PhysAddr, PhysPageNum, VirtAddr, VirtPageNum};
 use super::{StepByOne, VPNRange};
-use crate::config::{MEMORY_END, PAGE_SIZE, TRAMPOLINE, TRAP_CONTEXT, USER_STACK_SIZE};
+use crate::config::{MEMORY_END, MMAP_BASE, PAGE_SIZE, TRAMPOLINE, TRAP_CONTEXT, USER_SPACE_END, USER_STACK_SIZE};
 use alloc::collections::BTreeMap;
 use alloc::sync::Arc;
+use alloc::vec;
 use alloc::vec::Vec;
 use lazy_static::*;
 use riscv::register::satp;
@@ -33,21 +35,34 @@ lazy_static! {
 
 /// memory set structure, controls virtual-memory space
 pub struct MemorySet {
-    page_table: PageTable,
+    /// declared before the page table, so that the areas are dropped, and unmap
+    /// themselves, while it is still there
     areas: Vec<MapArea>,
+    page_table: PageTable,
+    /// unmapped page right below the user stack, which must never be mapped
+    stack_guard: Option<VirtPageNum>,
 }
 
 impl MemorySet {
     pub fn new_bare() -> Self {
-        Self {
-            page_table: PageTable::new(),
+        Self::try_new_bare().unwrap()
+    }
+    /// Like `new_bare`, but returns `None` if there is no frame for the root table.
+    pub fn try_new_bare() -> Option<Self> {
+        Some(Self {
+            page_table: PageTable::new()?,
             areas: Vec::new(),
-        }
+            stack_guard: None,
+        })
     }
     pub fn token(&self) -> usize {
         self.page_table.token()
     }
     /// Assume that no conflicts.
+    ///
+    /// Panics when frames run out, so it is only for the kernel space built at
+    /// boot and for tests; user and kernel-stack areas go through
+    /// `try_insert_framed_area`.
     pub fn insert_framed_area(
         &mut self,
         start_va: VirtAddr,
@@ -59,20 +74,42 @@ impl MemorySet {
             None,
         );
     }
+    /// Panics when frames run out, see `insert_framed_area`.
     fn push(&mut self, mut map_area: MapArea, data: Option<&[u8]>) {
         map_area.map(&mut self.page_table);
         if let Some(data) = data {
             map_area.copy_data(&mut self.page_table, data);
         }
+        self.add_area(map_area);
+    }
+    /// Like `push` for a framed area, but maps nothing and returns false when
+    /// running out of frames.
+    fn try_push(&mut self, mut map_area: MapArea, data: Option<&[u8]>) -> bool {
+        if !map_area.try_map(&mut self.page_table) {
+            return false;
+        }
+        if let Some(data) = data {
+            map_area.copy_data(&mut self.page_table, data);
+        }
+        self.add_area(map_area);
+        true
+    }
+    /// Add an area whose pages are mapped in the page table of this memory set.
+    fn add_area(&mut self, mut map_area: MapArea) {
+        map_area.page_table_token = Some(self.page_table.token());
         self.areas.push(map_area);
     }
     /// Mention that trampoline is not collected by areas.
     fn map_trampoline(&mut self) {
-        self.page_table.map(
+        self.try_map_trampoline().unwrap();
+    }
+    /// Like `map_trampoline`, but returns `Err` if frames for the tables run out.
+    fn try_map_trampoline(&mut self) -> Result<(), ()> {
+        self.page_table.try_map(
             VirtAddr::from(TRAMPOLINE).into(),
             PhysAddr::from(strampoline as usize).into(),
             PTEFlags::R | PTEFlags::X,
-        );
+        )
     }
     /// Without kernel stacks.
     pub fn new_kernel() -> Self {
@@ -141,10 +178,13 @@ impl MemorySet {
     }
     /// Include sections in elf and trampoline and TrapContext and user stack,
     /// also returns user_sp and entry point.
-    pub fn from_elf(elf_data: &[u8]) -> (Self, usize, usize) {
-        let mut memory_set = Self::new_bare();
+    ///
+    /// Returns `None` if frames run out; whatever has been mapped so far is
+    /// released along with the unfinished memory set.
+    pub fn from_elf(elf_data: &[u8]) -> Option<(Self, usize, usize)> {
+        let mut memory_set = Self::try_new_bare()?;
         // map trampoline
-        memory_set.map_trampoline();
+        memory_set.try_map_trampoline().ok()?;
         // map program headers of elf, with U flag
         let elf = xmas_elf::ElfFile::new(elf_data).unwrap();
         let elf_header = elf.header;
@@ -170,19 +210,22 @@ impl MemorySet {
                 }
                 let map_area = MapArea::new(start_va, end_va, MapType::Framed, map_perm);
                 max_end_vpn = map_area.vpn_range.get_end();
-                memory_set.push(
+                if !memory_set.try_push(
                     map_area,
                     Some(&elf.input[ph.offset() as usize..(ph.offset() + ph.file_size()) as usize]),
-                );
+                ) {
+                    return None;
+                }
             }
         }
         // map user stack with U flags
         let max_end_va: VirtAddr = max_end_vpn.into();
         let mut user_stack_bottom: usize = max_end_va.into();
         // guard page
+        memory_set.stack_guard = Some(max_end_vpn);
         user_stack_bottom += PAGE_SIZE;
         let user_stack_top = user_stack_bottom + USER_STACK_SIZE;
-        memory_set.push(
+        if !memory_set.try_push(
             MapArea::new(
                 user_stack_bottom.into(),
                 user_stack_top.into(),
@@ -190,9 +233,23 @@ impl MemorySet {
                 MapPermission::R | MapPermission::W | MapPermission::U,
             ),
             None,
-        );
+        ) {
+            return None;
+        }
+        // the heap starts empty right above the user stack and grows with sbrk
+        if !memory_set.try_push(
+            MapArea::new(
+                user_stack_top.into(),
+                user_stack_top.into(),
+                MapType::Framed,
+                MapPermission::R | MapPermission::W | MapPermission::U,
+            ),
+            None,
+        ) {
+            return None;
+        }
         // map TrapContext
-        memory_set.push(
+        if !memory_set.try_push(
             MapArea::new(
                 TRAP_CONTEXT.into(),
                 TRAMPOLINE.into(),
@@ -200,49 +257,447 @@ impl MemorySet {
                 MapPermission::R | MapPermission::W,
             ),
             None,
-        );
-        (
+        ) {
+            return None;
+        }
+        Some((
             memory_set,
             user_stack_top,
             elf.header.pt2.entry_point() as usize,
-        )
+        ))
     }
     pub fn activate(&self) {
         let satp = self.page_table.token();
         unsafe {
             satp::write(satp);
-            core::arch::asm!("sfence.vma");
         }
+        flush_tlb_all();
     }
     pub fn translate(&self, vpn: VirtPageNum) -> Option<PageTableEntry> {
         self.page_table.translate(vpn)
     }
+    /// Translate a virtual address to its physical address, see [`PageTable::translate_va`].
+    pub fn translate_va(&self, va: VirtAddr) -> Option<PhysAddr> {
+        self.page_table.translate_va(va)
+    }
     
+    /// Whether any page in `[start_va.floor(), end_va.ceil())` is already mapped,
+    /// or reserved by a lazy area or as the stack guard page.
+    ///
+    /// Every user mapping belongs to an area, so the range is only checked against
+    /// the areas, whatever its size.
     pub fn conflict_with_range(&self, start_va: VirtAddr, end_va: VirtAddr) -> bool {
-        self.areas.iter().find(|&area|{
-            area.conflict_with_range(start_va, end_va)
-        }).is_some()
-    }
-    pub fn unmap_area_by_exact_range(&mut self, start_vn: VirtPageNum, end_vn: VirtPageNum) -> isize {
-        for i in 0..self.areas.len(){
-            let area = &mut self.areas[i];
-            let vrange = area.vpn_range;
-            if start_vn == vrange.get_start() && end_vn == vrange.get_end() {
-                area.unmap(&mut self.page_table);
-                self.areas.swap_remove(i);
-                return 0;
+        let start = start_va.floor();
+        let end = end_va.ceil();
+        start < end
+            && (self.areas.iter().filter(|area| !area.is_empty()).any(|area| {
+                area.vpn_range.get_start() < end && start < area.vpn_range.get_end()
+            }) || self
+                .stack_guard
+                .map_or(false, |vpn| start <= vpn && vpn < end))
+    }
+    /// First free range of `len` bytes at or above `MMAP_BASE`, found by a first-fit
+    /// scan over the gaps between areas. Returns its page-aligned start, or `None`
+    /// if no gap below `USER_SPACE_END` is large enough.
+    pub fn find_free_area(&self, len: usize) -> Option<VirtAddr> {
+        let pages = (len + PAGE_SIZE - 1) / PAGE_SIZE;
+        let mut used: Vec<(VirtPageNum, VirtPageNum)> = self
+            .areas
+            .iter()
+            .map(|area| (area.vpn_range.get_start(), area.vpn_range.get_end()))
+            .chain(self.stack_guard.map(|vpn| (vpn, VirtPageNum(vpn.0 + 1))))
+            .collect();
+        used.sort();
+        let mut candidate = VirtAddr::from(MMAP_BASE).floor();
+        for (start, end) in used {
+            if end <= candidate {
+                continue;
+            }
+            if start.0 >= candidate.0 + pages {
+                break;
             }
+            candidate = end;
         }
-        -1
+        if candidate.0 + pages <= VirtAddr::from(USER_SPACE_END).floor().0 {
+            Some(candidate.into())
+        } else {
+            None
+        }
+    }
+    /// Whether `va` lies in the guard page below the user stack.
+    pub fn in_stack_guard(&self, va: VirtAddr) -> bool {
+        self.stack_guard == Some(va.floor())
+    }
+    /// Insert a lazy area, whose frames are allocated on the first access to each page.
+    /// Assume that no conflicts.
+    pub fn insert_lazy_area(
+        &mut self,
+        start_va: VirtAddr,
+        end_va: VirtAddr,
+        permission: MapPermission,
+    ) {
+        self.add_area(MapArea::new(start_va, end_va, MapType::Lazy, permission));
+    }
+    /// Index of the lazy area containing `vpn`, if any.
+    fn lazy_area_of(&self, vpn: VirtPageNum) -> Option<usize> {
+        self.areas.iter().position(|area| {
+            area.map_type == MapType::Lazy
+                && area.vpn_range.get_start() <= vpn
+                && vpn < area.vpn_range.get_end()
+        })
+    }
+    /// Handle a page fault at `va`, either by allocating the frame of a lazy page
+    /// or by giving a copy-on-write page a private writable frame.
+    /// Returns false if the access is really not permitted or frames run out.
+    pub fn handle_page_fault(&mut self, va: VirtAddr) -> bool {
+        let vpn = va.floor();
+        if let Some(pte) = self.page_table.translate(vpn).filter(|pte| pte.is_valid()) {
+            return pte.flags().contains(PTEFlags::COW) && self.resolve_cow(vpn);
+        }
+        match self.lazy_area_of(vpn) {
+            Some(idx) => self.areas[idx].map_lazy_one(&mut self.page_table, vpn),
+            None => false,
+        }
+    }
+    /// Unmap and drop the area starting at `start_vpn`, if there is one.
+    pub fn remove_area_with_start_vpn(&mut self, start_vpn: VirtPageNum) {
+        if let Some(idx) = self
+            .areas
+            .iter()
+            .position(|area| area.vpn_range.get_start() == start_vpn)
+        {
+            let mut area = self.areas.remove(idx);
+            area.unmap(&mut self.page_table);
+        }
+    }
+    /// Insert a framed area only if frames for all of its pages can be allocated,
+    /// otherwise map nothing and return false.
+    pub fn try_insert_framed_area(
+        &mut self,
+        start_va: VirtAddr,
+        end_va: VirtAddr,
+        permission: MapPermission,
+    ) -> bool {
+        let mut map_area = MapArea::new(start_va, end_va, MapType::Framed, permission);
+        if !map_area.try_map(&mut self.page_table) {
+            return false;
+        }
+        self.add_area(map_area);
+        true
+    }
+    /// Grow the framed area starting at `start` so that it covers `new_end`.
+    /// Returns false, leaving the area unchanged, if the new pages overlap another
+    /// mapping or frames run out.
+    pub fn append_to(&mut self, start: VirtAddr, new_end: VirtAddr) -> bool {
+        let idx = match self
+            .areas
+            .iter()
+            .position(|area| area.vpn_range.get_start() == start.floor())
+        {
+            Some(idx) => idx,
+            None => return false,
+        };
+        let old_end: VirtAddr = self.areas[idx].vpn_range.get_end().into();
+        if self.conflict_with_range(old_end, new_end) {
+            return false;
+        }
+        self.areas[idx].try_append_to(&mut self.page_table, new_end.ceil())
+    }
+    /// Shrink the framed area starting at `start` so that it ends at `new_end`,
+    /// freeing the frames of the pages beyond it.
+    pub fn shrink_to(&mut self, start: VirtAddr, new_end: VirtAddr) -> bool {
+        match self
+            .areas
+            .iter_mut()
+            .find(|area| area.vpn_range.get_start() == start.floor())
+        {
+            Some(area) => {
+                area.shrink_to(&mut self.page_table, new_end.ceil());
+                true
+            }
+            None => false,
+        }
+    }
+    /// Map the shared `frames` from `start_va` on with R|W|U, returning false if
+    /// any of the pages is already in use.
+    pub fn attach_shared(&mut self, start_va: VirtAddr, frames: Vec<Arc<FrameTracker>>) -> bool {
+        let end_va: VirtAddr = (usize::from(start_va) + frames.len() * PAGE_SIZE).into();
+        if self.conflict_with_range(start_va, end_va) {
+            return false;
+        }
+        let mut map_area = MapArea::new(
+            start_va,
+            end_va,
+            MapType::Shared,
+            MapPermission::R | MapPermission::W | MapPermission::U,
+        );
+        let pte_flags = PTEFlags::from_bits(map_area.map_perm.bits as u16).unwrap();
+        for (vpn, frame) in map_area.vpn_range.into_iter().zip(frames) {
+            self.page_table.map(vpn, frame.ppn, pte_flags);
+            map_area.data_frames.insert(vpn, frame);
+        }
+        self.add_area(map_area);
+        true
+    }
+    /// Unmap and drop all areas of an exited task, releasing their frames.
+    /// The page table is kept, as it is freed along with the whole memory set,
+    /// but it no longer refers to any released frame.
+    pub fn recycle_data_pages(&mut self) {
+        for mut area in self.areas.drain(..) {
+            area.unmap(&mut self.page_table);
+        }
+    }
+    /// Resolve every copy-on-write page in `[start_va, end_va)` ahead of a write by
+    /// the kernel, which goes through physical addresses and would not fault.
+    /// Pages that are not copy-on-write are left alone.
+    pub fn break_cow_range(&mut self, start_va: VirtAddr, end_va: VirtAddr) {
+        for vpn in VPNRange::new(start_va.floor(), end_va.ceil()) {
+            if self
+                .page_table
+                .translate(vpn)
+                .map_or(false, |pte| pte.is_valid() && pte.flags().contains(PTEFlags::COW))
+            {
+                self.resolve_cow(vpn);
+            }
+        }
+    }
+    /// Make a copy-on-write page writable again, copying its frame if it is still shared.
+    fn resolve_cow(&mut self, vpn: VirtPageNum) -> bool {
+        let mut flags = self.page_table.translate(vpn).unwrap().flags();
+        flags.remove(PTEFlags::COW);
+        flags.insert(PTEFlags::W);
+        let frame = match self
+            .areas
+            .iter_mut()
+            .find_map(|area| area.data_frames.get_mut(&vpn))
+        {
+            Some(frame) => frame,
+            None => return false,
+        };
+        if Arc::strong_count(frame) > 1 {
+            let new_frame = match frame_alloc() {
+                Some(new_frame) => new_frame,
+                None => return false,
+            };
+            new_frame.ppn.copy_page_from(frame.ppn);
+            *frame = Arc::new(new_frame);
+        }
+        self.page_table.remap(vpn, frame.ppn, flags).unwrap();
+        true
+    }
+    /// Duplicate this address space for a forked task.
+    ///
+    /// User pages are shared copy-on-write with the child, except those of shared
+    /// memory segments, which the child maps as they are so that both sides keep
+    /// writing to the segment. The trap context, which is written by the kernel
+    /// directly, gets private frames with a copy of its data.
+    ///
+    /// Returns `None` if frames run out. The child's frames and tables are released
+    /// then; pages of this address space already turned copy-on-write stay so,
+    /// which only costs a fault on their next write.
+    pub fn clone_cow(&mut self) -> Option<Self> {
+        let mut memory_set = Self::try_new_bare()?;
+        memory_set.try_map_trampoline().ok()?;
+        memory_set.stack_guard = self.stack_guard;
+        for area in self.areas.iter() {
+            let mut new_area = MapArea::from_another(area);
+            if area.map_perm.contains(MapPermission::U) {
+                // the area joins the child first, so that its pages are unmapped
+                // from the child if a later page fails
+                new_area.page_table_token = Some(memory_set.page_table.token());
+                for (vpn, frame) in area.data_frames.iter() {
+                    let shared = if area.map_type == MapType::Shared {
+                        let flags = self.page_table.translate(*vpn).unwrap().flags();
+                        memory_set.page_table.try_map(*vpn, frame.ppn, flags)
+                    } else {
+                        self.page_table.share_cow(&mut memory_set.page_table, *vpn)
+                    };
+                    shared.ok()?;
+                    new_area.data_frames.insert(*vpn, frame.clone());
+                }
+                memory_set.add_area(new_area);
+            } else {
+                if !memory_set.try_push(new_area, None) {
+                    return None;
+                }
+                for vpn in area.vpn_range {
+                    let src_ppn = self.page_table.translate(vpn).unwrap().ppn();
+                    let dst_ppn = memory_set.translate(vpn).unwrap().ppn();
+                    dst_ppn.copy_page_from(src_ppn);
+                }
+            }
+        }
+        Some(memory_set)
+    }
+    /// Whether every page in `[start_vn, end_vn)` is mapped and accessible from user mode,
+    /// counting pages of lazy areas as mapped.
+    pub fn range_all_mapped(&self, start_vn: VirtPageNum, end_vn: VirtPageNum) -> bool {
+        VPNRange::new(start_vn, end_vn).into_iter().all(|vpn| {
+            self.page_table
+                .translate(vpn)
+                .map_or(false, |pte| pte.is_valid() && pte.flags().contains(PTEFlags::U))
+                || self.lazy_area_of(vpn).is_some()
+        })
+    }
+    /// Number of user pages currently backed by a frame; untouched lazy pages are not counted.
+    pub fn resident_pages(&self) -> usize {
+        self.user_areas().map(|area| area.data_frames.len()).sum()
+    }
+    /// Number of user areas, including the (possibly empty) heap.
+    pub fn user_area_count(&self) -> usize {
+        self.user_areas().count()
+    }
+    /// Page ranges `[start, end)` of the user areas, including lazy ones.
+    pub fn user_ranges(&self) -> impl Iterator<Item = (VirtPageNum, VirtPageNum)> + '_ {
+        self.user_areas()
+            .map(|area| (area.vpn_range.get_start(), area.vpn_range.get_end()))
+    }
+    fn user_areas(&self) -> impl Iterator<Item = &MapArea> {
+        self.areas
+            .iter()
+            .filter(|area| area.map_perm.contains(MapPermission::U))
+    }
+    /// One status byte for each page in `[start_vn, end_vn)`, with bit 0 set if the
+    /// page has been accessed and bit 1 if it is dirty. Pages of lazy areas that have
+    /// never been touched read as 0. Returns `None` if any page is not mapped.
+    pub fn page_status(&self, start_vn: VirtPageNum, end_vn: VirtPageNum) -> Option<Vec<u8>> {
+        if !self.range_all_mapped(start_vn, end_vn) {
+            return None;
+        }
+        Some(
+            VPNRange::new(start_vn, end_vn)
+                .into_iter()
+                .map(|vpn| match self.page_table.translate(vpn) {
+                    Some(pte) if pte.is_valid() => pte.accessed() as u8 | (pte.dirty() as u8) << 1,
+                    _ => 0,
+                })
+                .collect(),
+        )
+    }
+    /// Clear the accessed and dirty bits of every present page in `[start_vn, end_vn)`.
+    /// Nothing is changed and -1 is returned if any page in the range is not mapped.
+    pub fn clear_refs(&mut self, start_vn: VirtPageNum, end_vn: VirtPageNum) -> isize {
+        if !self.range_all_mapped(start_vn, end_vn) {
+            return -1;
+        }
+        for vpn in VPNRange::new(start_vn, end_vn) {
+            // untouched lazy pages have nothing to clear
+            let _ = self.page_table.clear_refs(vpn);
+        }
+        0
+    }
+    /// Flag bits of the valid page table entry of `vpn`, None if it is not present.
+    pub fn pte_flags(&self, vpn: VirtPageNum) -> Option<usize> {
+        self.page_table
+            .translate(vpn)
+            .filter(|pte| pte.is_valid())
+            .map(|pte| pte.flags().bits() as usize)
+    }
+    /// Change the permission of every page in `[start_vn, end_vn)` without remapping.
+    /// Nothing is changed and -1 is returned if any page in the range is not a mapped
+    /// user page, or if frames run out for its lazy pages.
+    ///
+    /// A page whose frame is still shared copy-on-write does not become writable
+    /// right away, it keeps the `COW` bit instead and is copied on the first write.
+    pub fn protect_range(
+        &mut self,
+        start_vn: VirtPageNum,
+        end_vn: VirtPageNum,
+        permission: MapPermission,
+    ) -> isize {
+        if !self.range_all_mapped(start_vn, end_vn) {
+            return -1;
+        }
+        // lazy pages are populated first so that they keep the new permission,
+        // all of them before any flag changes so that running out of frames changes nothing
+        let mut populated = Vec::new();
+        for vpn in VPNRange::new(start_vn, end_vn) {
+            if self.page_table.translate(vpn).map_or(false, |pte| pte.is_valid()) {
+                continue;
+            }
+            if !self.handle_page_fault(vpn.into()) {
+                for vpn in populated {
+                    self.unmap_lazy_page(vpn);
+                }
+                return -1;
+            }
+            populated.push(vpn);
+        }
+        let flags = PTEFlags::from_bits(permission.bits as u16).unwrap();
+        for vpn in VPNRange::new(start_vn, end_vn) {
+            let mut pte_flags = flags;
+            if pte_flags.contains(PTEFlags::W) && self.is_cow_shared(vpn) {
+                pte_flags.remove(PTEFlags::W);
+                pte_flags.insert(PTEFlags::COW);
+            }
+            self.page_table.set_flags(vpn, pte_flags).unwrap();
+        }
+        0
+    }
+    /// Give the frame of the populated lazy page `vpn` back, leaving it untouched again.
+    fn unmap_lazy_page(&mut self, vpn: VirtPageNum) {
+        let idx = self.lazy_area_of(vpn).unwrap();
+        self.page_table.unmap(vpn);
+        self.areas[idx].data_frames.remove(&vpn);
+    }
+    /// Whether the frame of `vpn` is also mapped by a forked address space, so it
+    /// has to be copied before this one writes to it. Shared memory is never copied.
+    fn is_cow_shared(&self, vpn: VirtPageNum) -> bool {
+        self.areas
+            .iter()
+            .filter(|area| area.map_type != MapType::Shared)
+            .find_map(|area| area.data_frames.get(&vpn))
+            .map_or(false, |frame| Arc::strong_count(frame) > 1)
+    }
+    /// Unmap every page in `[start_vn, end_vn)`, splitting the areas it cuts through.
+    /// Nothing is changed and -1 is returned if any page in the range is not a mapped user page.
+    pub fn unmap_range(&mut self, start_vn: VirtPageNum, end_vn: VirtPageNum) -> isize {
+        if !self.range_all_mapped(start_vn, end_vn) {
+            return -1;
+        }
+        let mut split_areas = Vec::new();
+        for area in self.areas.iter_mut() {
+            let start = area.vpn_range.get_start();
+            let end = area.vpn_range.get_end();
+            // the tail beyond the range is kept, the middle piece is unmapped below
+            if start < end_vn && end_vn < end {
+                split_areas.push(area.split_off(end_vn));
+            }
+            if start < start_vn && start_vn < end {
+                split_areas.push(area.split_off(start_vn));
+            }
+        }
+        self.areas.extend(split_areas);
+        let page_table = &mut self.page_table;
+        self.areas.retain_mut(|area| {
+            // an empty heap has no page to unmap and must stay for sbrk
+            if area.inside(start_vn, end_vn) && !area.is_empty() {
+                area.unmap(page_table);
+                false
+            } else {
+                true
+            }
+        });
+        0
     }
 }
 
 /// map area structure, controls a contiguous piece of virtual memory
+///
+/// An area owns the frames of its pages, which are released when it is dropped.
+/// Once added to a memory set it also unmaps those pages from its page table on
+/// drop, so no entry is left pointing to a released frame. Identical pages have
+/// no frame and stay mapped. Unmapping explicitly, as [`MemorySet::unmap_range`]
+/// and [`MemorySet::recycle_data_pages`] do, reclaims the emptied page tables too.
 pub struct MapArea {
     vpn_range: VPNRange,
-    data_frames: BTreeMap<VirtPageNum, FrameTracker>,
+    /// frames shared by forked address spaces are reference counted for copy-on-write
+    data_frames: BTreeMap<VirtPageNum, Arc<FrameTracker>>,
     map_type: MapType,
     map_perm: MapPermission,
+    /// token of the page table the pages are mapped in, `None` before the area
+    /// is added to a memory set
+    page_table_token: Option<usize>,
 }
 
 impl MapArea {
@@ -259,47 +714,136 @@ impl MapArea {
             data_frames: BTreeMap::new(),
             map_type,
             map_perm,
+            page_table_token: None,
         }
     }
-    pub fn map_one(&mut self, page_table: &mut PageTable, vpn: VirtPageNum) {
+    /// An empty area with the same range, type and permission as `another`,
+    /// not added to any memory set yet.
+    pub fn from_another(another: &MapArea) -> Self {
+        Self {
+            vpn_range: VPNRange::new(another.vpn_range.get_start(), another.vpn_range.get_end()),
+            data_frames: BTreeMap::new(),
+            map_type: another.map_type,
+            map_perm: another.map_perm,
+            page_table_token: None,
+        }
+    }
+    /// Map `vpn` of an identical or framed area.
+    ///
+    /// Panics when frames run out; only `map` calls it, for areas that may
+    /// assume memory (see `MemorySet::insert_framed_area`).
+    fn map_one(&mut self, page_table: &mut PageTable, vpn: VirtPageNum) {
         let ppn: PhysPageNum;
         match self.map_type {
             MapType::Identical => {
                 ppn = PhysPageNum(vpn.0);
             }
-            MapType::Framed => {
+            MapType::Shared => unreachable!("shared areas are mapped from their segment"),
+            MapType::Framed | MapType::Lazy => {
                 let frame = frame_alloc().unwrap();
                 ppn = frame.ppn;
-                self.data_frames.insert(vpn, frame);
+                self.data_frames.insert(vpn, Arc::new(frame));
             }
         }
-        let pte_flags = PTEFlags::from_bits(self.map_perm.bits).unwrap();
+        let pte_flags = PTEFlags::from_bits(self.map_perm.bits as u16).unwrap();
         page_table.map(vpn, ppn, pte_flags);
     }
-    pub fn unmap_one(&mut self, page_table: &mut PageTable, vpn: VirtPageNum) {
-        #[allow(clippy::single_match)]
-        match self.map_type {
-            MapType::Framed => {
-                self.data_frames.remove(&vpn);
-            }
-            _ => {}
-        }
-        page_table.unmap(vpn);
-    }
+    /// Map every page of the area, panicking when frames run out; fallible
+    /// callers use `try_map`.
     pub fn map(&mut self, page_table: &mut PageTable) {
         for vpn in self.vpn_range {
             self.map_one(page_table, vpn);
         }
     }
+    /// Map one page of a lazy area on demand, returning false when running out of frames.
+    pub fn map_lazy_one(&mut self, page_table: &mut PageTable, vpn: VirtPageNum) -> bool {
+        assert_eq!(self.map_type, MapType::Lazy);
+        let pte_flags = PTEFlags::from_bits(self.map_perm.bits as u16).unwrap();
+        match frame_alloc().filter(|frame| page_table.try_map(vpn, frame.ppn, pte_flags).is_ok()) {
+            Some(frame) => {
+                self.data_frames.insert(vpn, Arc::new(frame));
+                true
+            }
+            None => false,
+        }
+    }
     pub fn unmap(&mut self, page_table: &mut PageTable) {
-        for vpn in self.vpn_range {
-            self.unmap_one(page_table, vpn);
+        if self.map_type == MapType::Lazy {
+            // only the pages which have been accessed are present
+            for vpn in self.data_frames.keys() {
+                page_table.unmap(*vpn);
+            }
+        } else {
+            page_table
+                .unmap_range(self.vpn_range.get_start(), self.vpn_range.get_end())
+                .unwrap();
         }
+        self.data_frames.clear();
     }
-    pub fn conflict_with_range(&self, start_va: VirtAddr, end_va: VirtAddr) -> bool {
-        let self_start: VirtAddr = self.vpn_range.get_start().into();
-        let self_end: VirtAddr = self.vpn_range.get_end().into();
-        start_va < self_end && end_va > self_start 
+    /// Map all pages of a framed area, mapping nothing and returning false
+    /// when running out of frames.
+    pub fn try_map(&mut self, page_table: &mut PageTable) -> bool {
+        assert_eq!(self.map_type, MapType::Framed);
+        let pte_flags = PTEFlags::from_bits(self.map_perm.bits as u16).unwrap();
+        match page_table.map_range(self.vpn_range.get_start(), self.vpn_range.get_end(), pte_flags) {
+            Ok(frames) => {
+                self.data_frames
+                    .extend(frames.into_iter().map(|(vpn, frame)| (vpn, Arc::new(frame))));
+                true
+            }
+            Err(()) => false,
+        }
+    }
+    /// Map the pages in `[end, new_end)` and extend this framed area to `new_end`,
+    /// mapping nothing and returning false when running out of frames.
+    pub fn try_append_to(&mut self, page_table: &mut PageTable, new_end: VirtPageNum) -> bool {
+        assert_eq!(self.map_type, MapType::Framed);
+        let start = self.vpn_range.get_start();
+        let end = self.vpn_range.get_end();
+        if new_end > end {
+            let pte_flags = PTEFlags::from_bits(self.map_perm.bits as u16).unwrap();
+            match page_table.map_range(end, new_end, pte_flags) {
+                Ok(frames) => self
+                    .data_frames
+                    .extend(frames.into_iter().map(|(vpn, frame)| (vpn, Arc::new(frame)))),
+                Err(()) => return false,
+            }
+            self.vpn_range = VPNRange::new(start, new_end);
+        }
+        true
+    }
+    /// Unmap the pages in `[new_end, end)` and cut this framed area down to `new_end`.
+    pub fn shrink_to(&mut self, page_table: &mut PageTable, new_end: VirtPageNum) {
+        assert_eq!(self.map_type, MapType::Framed);
+        let start = self.vpn_range.get_start();
+        let end = self.vpn_range.get_end();
+        if new_end < end {
+            page_table.unmap_range(new_end, end).unwrap();
+            // dropping the trackers frees the frames
+            self.data_frames.split_off(&new_end);
+            self.vpn_range = VPNRange::new(start, new_end);
+        }
+    }
+    /// Split this area at `at`, keeping `[start, at)` and returning `[at, end)`
+    /// along with its frames. The page table is not changed.
+    pub fn split_off(&mut self, at: VirtPageNum) -> MapArea {
+        let start = self.vpn_range.get_start();
+        let end = self.vpn_range.get_end();
+        assert!(start < at && at < end);
+        let mut tail = MapArea::from_another(self);
+        tail.page_table_token = self.page_table_token;
+        tail.vpn_range = VPNRange::new(at, end);
+        tail.data_frames = self.data_frames.split_off(&at);
+        self.vpn_range = VPNRange::new(start, at);
+        tail
+    }
+    /// Whether this area has no page, like the heap before it grows.
+    pub fn is_empty(&self) -> bool {
+        self.vpn_range.get_start() == self.vpn_range.get_end()
+    }
+    /// Whether this area lies entirely within `[start_vn, end_vn)`.
+    pub fn inside(&self, start_vn: VirtPageNum, end_vn: VirtPageNum) -> bool {
+        start_vn <= self.vpn_range.get_start() && self.vpn_range.get_end() <= end_vn
     }
     /// data: start-aligned but maybe with shorter length
     /// assume that all frames were cleared before
@@ -325,11 +869,26 @@ impl MapArea {
     }
 }
 
+impl Drop for MapArea {
+    fn drop(&mut self) {
+        if let Some(token) = self.page_table_token {
+            // the tables emptied here are reclaimed along with the page table
+            let mut page_table = PageTable::from_token(token);
+            for vpn in self.data_frames.keys() {
+                page_table.try_unmap(*vpn).unwrap();
+            }
+        }
+    }
+}
+
 #[derive(Copy, Clone, PartialEq, Debug)]
-/// map type for memory set: identical or framed
+/// map type for memory set: identical, framed, lazy (framed on demand) or
+/// shared (frames of a shared memory segment, never copy-on-write)
 pub enum MapType {
     Identical,
     Framed,
+    Lazy,
+    Shared,
 }
 
 bitflags! {
@@ -365,3 +924,378 @@ pub fn remap_test() {
         .executable());
     info!("remap_test passed!");
 }
+
+#[allow(unused)]
+/// identical areas map every page to the frame of the same number and own no frame
+pub fn identical_area_test() {
+    let kernel_space = KERNEL_SPACE.lock();
+    for va in [stext as usize, srodata as usize, sdata as usize, ekernel as usize, MEMORY_END - 1] {
+        let vpn = VirtAddr::from(va).floor();
+        assert_eq!(kernel_space.translate(vpn).unwrap().ppn().0, vpn.0);
+    }
+    drop(kernel_space);
+    let before = free_frame_count();
+    let mut memory_set = MemorySet::new_bare();
+    // within one leaf table, as MEMORY_END is 2MiB-aligned
+    let end = MEMORY_END;
+    let base = end - 4 * PAGE_SIZE;
+    memory_set.push(
+        MapArea::new(base.into(), end.into(), MapType::Identical, MapPermission::R),
+        None,
+    );
+    for vpn in VPNRange::new(VirtAddr::from(base).floor(), VirtAddr::from(end).ceil()) {
+        let pte = memory_set.translate(vpn).unwrap();
+        assert!(pte.is_valid() && pte.readable() && !pte.writable());
+        assert_eq!(pte.ppn(), PhysPageNum(vpn.0));
+    }
+    // a root, a level-1 and a leaf table, no data frame
+    assert_eq!(free_frame_count(), before - 3);
+    memory_set.recycle_data_pages();
+    drop(memory_set);
+    assert_eq!(free_frame_count(), before);
+    info!("identical_area_test passed!");
+}
+
+#[allow(unused)]
+/// copy a buffer across page boundaries into a fresh user space and check every byte
+pub fn copy_kernel_to_user_test() {
+    let mut memory_set = MemorySet::new_bare();
+    let base: usize = 0x10000000;
+    memory_set.insert_framed_area(
+        base.into(),
+        (base + 3 * PAGE_SIZE).into(),
+        MapPermission::R | MapPermission::W | MapPermission::U,
+    );
+    let len: usize = 5000;
+    let dst = base + PAGE_SIZE - 100;
+    let src: Vec<u8> = (0..len).map(|i| i as u8).collect();
+    copy_kernel_to_user(memory_set.token(), src.as_ptr(), dst, len).unwrap();
+    for (i, byte) in src.iter().enumerate() {
+        let pa = memory_set.translate_va((dst + i).into()).unwrap();
+        assert_eq!(pa.get_ref::<u8>(), byte);
+    }
+    let mut back: Vec<u8> = vec![0; len];
+    copy_user_to_kernel(memory_set.token(), back.as_mut_ptr(), dst, len).unwrap();
+    assert_eq!(src, back);
+    // 大于一页的结构体整体拷贝
+    #[repr(C)]
+    struct Big {
+        head: usize,
+        body: [u8; PAGE_SIZE],
+        tail: usize,
+    }
+    let mut big = Big {
+        head: 0x1234_5678,
+        body: [0; PAGE_SIZE],
+        tail: 0x8765_4321,
+    };
+    for (i, byte) in big.body.iter_mut().enumerate() {
+        *byte = (i * 7) as u8;
+    }
+    copy_type_into_user(memory_set.token(), &big, dst).unwrap();
+    let size = core::mem::size_of::<Big>();
+    let mut back: Vec<u8> = vec![0; size];
+    copy_user_to_kernel(memory_set.token(), back.as_mut_ptr(), dst, size).unwrap();
+    let expected = unsafe { core::slice::from_raw_parts(&big as *const Big as *const u8, size) };
+    assert_eq!(expected, &back[..]);
+    let mut read_back = Big {
+        head: 0,
+        body: [0; PAGE_SIZE],
+        tail: 0,
+    };
+    copy_type_from_user(memory_set.token(), dst, &mut read_back).unwrap();
+    assert_eq!(read_back.head, big.head);
+    assert_eq!(read_back.body, big.body);
+    assert_eq!(read_back.tail, big.tail);
+    // 页内的对象可以直接通过引用读写
+    let slot = (base + 2 * PAGE_SIZE - core::mem::size_of::<usize>()) as *mut usize;
+    *translated_refmut(memory_set.token(), slot) = 0xdead_beef;
+    assert_eq!(*translated_ref(memory_set.token(), slot as *const usize), 0xdead_beef);
+    // 越过映射区域末尾的拷贝应当失败而不是 panic
+    assert!(copy_kernel_to_user(memory_set.token(), src.as_ptr(), base + 3 * PAGE_SIZE - 100, len).is_err());
+    assert!(check_user_ptr(memory_set.token(), dst, len, true));
+    assert!(!check_user_ptr(memory_set.token(), base + 3 * PAGE_SIZE - 100, len, false));
+    // 只读页可以读取，但不能作为写入的目标
+    memory_set.insert_framed_area(
+        (base + 3 * PAGE_SIZE).into(),
+        (base + 4 * PAGE_SIZE).into(),
+        MapPermission::R | MapPermission::U,
+    );
+    assert!(check_user_ptr(memory_set.token(), base + 3 * PAGE_SIZE - 8, 16, false));
+    assert!(!check_user_ptr(memory_set.token(), base + 3 * PAGE_SIZE - 8, 16, true));
+    // 一个 UserPtrReader 可以完成多次读写，失败的写入不改动任何一页
+    let user = UserPtrReader::new(memory_set.token());
+    assert_eq!(user.token(), memory_set.token());
+    let pair: (usize, usize) = (0x1111, 0x2222);
+    user.write_struct(base + PAGE_SIZE - 8, &pair).unwrap();
+    let mut pair_back: (usize, usize) = (0, 0);
+    user.read_struct(base + PAGE_SIZE - 8, &mut pair_back).unwrap();
+    assert_eq!(pair, pair_back);
+    assert!(user.write_struct(base + 3 * PAGE_SIZE - 8, &pair).is_err());
+    assert_eq!(*translated_ref(memory_set.token(), (base + 3 * PAGE_SIZE - 8) as *const usize), 0);
+    assert!(user.read_struct(base + 4 * PAGE_SIZE - 8, &mut pair_back).is_err());
+    assert_eq!(pair, pair_back);
+    info!("copy_kernel_to_user_test passed!");
+}
+
+#[allow(unused)]
+/// read back a null-terminated string whose terminator lies on the next page
+pub fn translated_str_test() {
+    let mut memory_set = MemorySet::new_bare();
+    let base: usize = 0x10000000;
+    memory_set.insert_framed_area(
+        base.into(),
+        (base + 2 * PAGE_SIZE).into(),
+        MapPermission::R | MapPermission::W | MapPermission::U,
+    );
+    let text = b"hello, page boundary\0";
+    let start = base + PAGE_SIZE - 7;
+    copy_kernel_to_user(memory_set.token(), text.as_ptr(), start, text.len()).unwrap();
+    let string = translated_str(memory_set.token(), start as *const u8).unwrap();
+    assert_eq!(string.as_bytes(), &text[..text.len() - 1]);
+    // 没有终止符的字符串一直读到未映射的页时返回 Err
+    let unterminated = [b'x'; 16];
+    let start = base + 2 * PAGE_SIZE - unterminated.len();
+    copy_kernel_to_user(memory_set.token(), unterminated.as_ptr(), start, unterminated.len()).unwrap();
+    assert!(translated_str(memory_set.token(), start as *const u8).is_err());
+    info!("translated_str_test passed!");
+}
+
+#[allow(unused)]
+/// a write to a page shared by fork must not be seen by the other address space
+pub fn cow_test() {
+    let mut parent = MemorySet::new_bare();
+    let base: usize = 0x10000000;
+    let vpn = VirtAddr::from(base).floor();
+    parent.insert_framed_area(
+        base.into(),
+        (base + PAGE_SIZE).into(),
+        MapPermission::R | MapPermission::W | MapPermission::U,
+    );
+    let parent_ppn = parent.translate(vpn).unwrap().ppn();
+    parent_ppn.get_bytes_array()[0] = 0x11;
+    let mut child = parent.clone_cow().unwrap();
+    for memory_set in [&parent, &child] {
+        let pte = memory_set.translate(vpn).unwrap();
+        assert_eq!(pte.ppn(), parent_ppn);
+        assert!(!pte.writable() && pte.flags().contains(PTEFlags::COW));
+    }
+    // store page fault in the child
+    assert!(child.handle_page_fault(base.into()));
+    let pte = child.translate(vpn).unwrap();
+    assert!(pte.writable() && !pte.flags().contains(PTEFlags::COW));
+    assert_ne!(pte.ppn(), parent_ppn);
+    assert_eq!(pte.ppn().get_bytes_array()[0], 0x11);
+    pte.ppn().get_bytes_array()[0] = 0x22;
+    assert_eq!(parent_ppn.get_bytes_array()[0], 0x11);
+    // the parent is the only owner now and keeps its frame
+    assert!(parent.handle_page_fault(base.into()));
+    let pte = parent.translate(vpn).unwrap();
+    assert!(pte.writable());
+    assert_eq!(pte.ppn(), parent_ppn);
+    // a fault outside every area is not resolved
+    assert!(!parent.handle_page_fault((base + PAGE_SIZE).into()));
+    // mprotect does not make a shared page writable, even through a read-only step
+    let mut child = parent.clone_cow().unwrap();
+    let end = VirtPageNum(vpn.0 + 1);
+    let rw = MapPermission::R | MapPermission::W | MapPermission::U;
+    assert_eq!(child.protect_range(vpn, end, MapPermission::R | MapPermission::U), 0);
+    assert!(!child.translate(vpn).unwrap().flags().contains(PTEFlags::COW));
+    assert_eq!(child.protect_range(vpn, end, rw), 0);
+    let pte = child.translate(vpn).unwrap();
+    assert!(!pte.writable() && pte.flags().contains(PTEFlags::COW));
+    assert_eq!(pte.ppn(), parent_ppn);
+    assert!(child.handle_page_fault(base.into()));
+    let pte = child.translate(vpn).unwrap();
+    assert!(pte.writable());
+    assert_ne!(pte.ppn(), parent_ppn);
+    pte.ppn().get_bytes_array()[0] = 0x33;
+    assert_eq!(parent_ppn.get_bytes_array()[0], 0x11);
+    // once the child has its own copy, the parent's page is writable at once
+    assert_eq!(parent.protect_range(vpn, end, rw), 0);
+    assert!(parent.translate(vpn).unwrap().writable());
+    info!("cow_test passed!");
+}
+
+#[allow(unused)]
+/// a large lazy area only costs frames for the pages actually touched
+pub fn lazy_area_test() {
+    let mut memory_set = MemorySet::new_bare();
+    let base: usize = 0x10000000;
+    let len: usize = 16 << 20;
+    let before = frame_allocated_count();
+    memory_set.insert_lazy_area(
+        base.into(),
+        (base + len).into(),
+        MapPermission::R | MapPermission::W | MapPermission::U,
+    );
+    assert_eq!(frame_allocated_count(), before);
+    assert_eq!(memory_set.resident_pages(), 0);
+    // touch the first and the last page
+    assert!(memory_set.handle_page_fault(base.into()));
+    assert!(memory_set.handle_page_fault((base + len - 1).into()));
+    // a second fault on a present page is not a lazy fault
+    assert!(!memory_set.handle_page_fault(base.into()));
+    // beyond the area
+    assert!(!memory_set.handle_page_fault((base + len).into()));
+    let lazy_area = &memory_set.areas[0];
+    assert_eq!(lazy_area.data_frames.len(), 2);
+    assert_eq!(memory_set.resident_pages(), 2);
+    // besides the two data frames, the pages need one level-1 table and
+    // one leaf table each, as they are 16MiB apart in the same 1GiB region
+    assert_eq!(frame_allocated_count() - before, 2 + 3);
+    // untouched lazy pages are reserved, checking a huge range costs no page walk
+    let va = |addr: usize| VirtAddr::from(addr);
+    assert!(memory_set.conflict_with_range(va(base + PAGE_SIZE), va(base + 2 * PAGE_SIZE)));
+    assert!(memory_set.conflict_with_range(va(base - PAGE_SIZE), va(base + 1)));
+    assert!(!memory_set.conflict_with_range(va(base - PAGE_SIZE), va(base)));
+    assert!(!memory_set.conflict_with_range(va(base + len), va(base + len + (1 << 37))));
+    assert!(!memory_set.conflict_with_range(va(base), va(base)));
+    // everything is released with the address space, including its root table
+    drop(memory_set);
+    assert_eq!(frame_allocated_count(), before - 1);
+    info!("lazy_area_test passed!");
+}
+
+#[allow(unused)]
+/// a framed area that runs out of frames part way maps nothing and leaks no frame
+pub fn oom_rollback_test() {
+    let mut memory_set = MemorySet::new_bare();
+    let base: usize = 0x10000000;
+    let permission = MapPermission::R | MapPermission::W | MapPermission::U;
+    let before = frame_allocated_count();
+    // hold every free frame
+    let mut hog = Vec::new();
+    while let Some(frame) = frame_alloc() {
+        hog.push(frame);
+    }
+    assert!(PageTable::new().is_none());
+    // 4 data frames plus a level-1 and a leaf table are needed, so every
+    // smaller number of free frames fails at a different point, e.g. with 4
+    // free frames the first two pages get mapped and the third one fails
+    for free in 0..6 {
+        hog.truncate(hog.len() - free);
+        let used = frame_allocated_count();
+        assert!(!memory_set.try_insert_framed_area(
+            base.into(),
+            (base + 4 * PAGE_SIZE).into(),
+            permission
+        ));
+        assert_eq!(frame_allocated_count(), used);
+        assert!(memory_set.translate(VirtAddr::from(base).floor()).is_none());
+        let mut refill = Vec::new();
+        while let Some(frame) = frame_alloc() {
+            refill.push(frame);
+        }
+        assert_eq!(refill.len(), free);
+        hog.extend(refill);
+    }
+    drop(hog);
+    assert_eq!(frame_allocated_count(), before);
+    assert!(memory_set.try_insert_framed_area(
+        base.into(),
+        (base + 4 * PAGE_SIZE).into(),
+        permission
+    ));
+    // mprotect running out of frames for the lazy pages part-way changes nothing
+    let lazy = base + 8 * PAGE_SIZE;
+    let lazy_vpn = VirtAddr::from(lazy).floor();
+    memory_set.insert_lazy_area(lazy.into(), (lazy + 4 * PAGE_SIZE).into(), permission);
+    assert!(memory_set.handle_page_fault(lazy.into()));
+    let mut hog = Vec::new();
+    while let Some(frame) = frame_alloc() {
+        hog.push(frame);
+    }
+    // enough for the second page only
+    hog.pop();
+    let used = frame_allocated_count();
+    assert_eq!(
+        memory_set.protect_range(
+            lazy_vpn,
+            VirtPageNum(lazy_vpn.0 + 4),
+            MapPermission::R | MapPermission::U
+        ),
+        -1
+    );
+    assert_eq!(frame_allocated_count(), used);
+    assert!(memory_set.translate(lazy_vpn).unwrap().writable());
+    assert!(memory_set
+        .translate(VirtPageNum(lazy_vpn.0 + 1))
+        .map_or(true, |pte| !pte.is_valid()));
+    // fork failing at any point gives back every frame the child got
+    let mut free = 0;
+    let child = loop {
+        let used = frame_allocated_count();
+        if let Some(child) = memory_set.clone_cow() {
+            break child;
+        }
+        assert_eq!(frame_allocated_count(), used);
+        free += 1;
+        hog.truncate(hog.len() - 1);
+        assert!(free < 8);
+    };
+    drop(hog);
+    assert!(free > 0);
+    drop(child);
+    // pages left copy-on-write by a failed fork are owned by the parent alone again
+    assert!(memory_set.handle_page_fault(base.into()));
+    assert!(memory_set.translate(VirtAddr::from(base).floor()).unwrap().writable());
+    info!("oom_rollback_test passed!");
+}
+
+#[allow(unused)]
+/// mapping and then unmapping areas, whole or in pieces, gives every frame back
+pub fn unmap_leak_test() {
+    let base: usize = 0x10000000;
+    let permission = MapPermission::R | MapPermission::W | MapPermission::U;
+    let before = free_frame_count();
+    // in use and free frames always add up to the same number
+    let total = before + frame_allocated_count();
+    let mut memory_set = MemorySet::new_bare();
+    let empty = free_frame_count();
+    assert_eq!(empty, before - 1);
+    assert!(memory_set.try_insert_framed_area(
+        base.into(),
+        (base + 16 * PAGE_SIZE).into(),
+        permission
+    ));
+    // 16 data frames plus a level-1 and a leaf table
+    assert_eq!(free_frame_count(), empty - 18);
+    assert_eq!(free_frame_count() + frame_allocated_count(), total);
+    let page = |n: usize| VirtAddr::from(base + n * PAGE_SIZE).floor();
+    assert_eq!(memory_set.unmap_range(page(6), page(10)), 0);
+    assert_eq!(free_frame_count(), empty - 14);
+    assert_eq!(memory_set.unmap_range(page(0), page(6)), 0);
+    assert_eq!(memory_set.unmap_range(page(10), page(16)), 0);
+    // the tables are reclaimed along with the last page
+    assert_eq!(free_frame_count(), empty);
+    // lazy pages that have been touched are given back as well
+    memory_set.insert_lazy_area(base.into(), (base + 16 * PAGE_SIZE).into(), permission);
+    assert!(memory_set.handle_page_fault(base.into()));
+    assert!(memory_set.handle_page_fault((base + 9 * PAGE_SIZE).into()));
+    assert_eq!(free_frame_count(), empty - 4);
+    assert_eq!(memory_set.unmap_range(page(0), page(16)), 0);
+    assert_eq!(free_frame_count(), empty);
+    // an exiting task unmaps all of its areas at once
+    assert!(memory_set.try_insert_framed_area(base.into(), (base + 4 * PAGE_SIZE).into(), permission));
+    memory_set.insert_lazy_area(
+        (base + 8 * PAGE_SIZE).into(),
+        (base + 12 * PAGE_SIZE).into(),
+        permission,
+    );
+    assert!(memory_set.handle_page_fault((base + 8 * PAGE_SIZE).into()));
+    memory_set.recycle_data_pages();
+    assert!(memory_set.translate(page(0)).map_or(true, |pte| !pte.is_valid()));
+    assert!(memory_set.translate(page(8)).map_or(true, |pte| !pte.is_valid()));
+    assert_eq!(free_frame_count(), empty);
+    // an area dropped without being unmapped first takes its pages out of the page table
+    assert!(memory_set.try_insert_framed_area(base.into(), (base + 2 * PAGE_SIZE).into(), permission));
+    drop(memory_set.areas.pop());
+    assert!(memory_set.translate(page(0)).map_or(true, |pte| !pte.is_valid()));
+    assert!(memory_set.translate(page(1)).map_or(true, |pte| !pte.is_valid()));
+    // the data frames are released at once, the two tables along with the page table
+    assert_eq!(free_frame_count(), empty - 2);
+    drop(memory_set);
+    assert_eq!(free_frame_count(), before);
+    info!("unmap_leak_test passed!");
+}
@@ -12,13 +12,25 @@ mod frame_allocator;
 mod heap_allocator;
 mod memory_set;
 mod page_table;
+mod shm;
 
 pub use address::{PhysAddr, PhysPageNum, VirtAddr, VirtPageNum};
 use address::{StepByOne, VPNRange};
-pub use frame_allocator::{frame_alloc, FrameTracker};
-pub use memory_set::remap_test;
-pub use memory_set::{MapPermission, MemorySet, KERNEL_SPACE};
-pub use page_table::{translated_byte_buffer, copy_kernel_to_user, PTEFlags, PageTable, PageTableEntry};
+pub use frame_allocator::{frame_alloc, frame_alloc_batch, frame_alloc_contiguous, FrameTracker};
+pub use frame_allocator::{frame_allocated_count, frame_stats, free_frame_count, FrameStats};
+pub use memory_set::{remap_test, MapPermission, MemorySet, KERNEL_SPACE};
+pub use shm::{shm_attach, shm_get, shm_release_detached};
+pub use page_table::{flush_tlb, flush_tlb_all};
+pub use page_table::{check_user_ptr, translated_byte_buffer, translated_ref, translated_refmut, translated_str, copy_kernel_to_user, copy_type_into_user, copy_type_from_user, copy_user_to_kernel, LookupResult, MmapPort, PTEFlags, PageTable, PageTableEntry, UserPtrReader};
+
+#[cfg(feature = "self-tests")]
+pub use address::va_rounding_test;
+#[cfg(feature = "self-tests")]
+pub use frame_allocator::{frame_alloc_batch_test, frame_alloc_contiguous_test, frame_zeroing_test, page_copy_test};
+#[cfg(feature = "self-tests")]
+pub use memory_set::{copy_kernel_to_user_test, cow_test, identical_area_test, lazy_area_test, oom_rollback_test, translated_str_test, unmap_leak_test};
+#[cfg(feature = "self-tests")]
+pub use page_table::{find_pte_create_test, iter_mappings_test, map_huge_test, map_range_test, port_flags_test, pte_debug_test};
 
 /// initiate heap allocator, frame allocator and kernel space
 pub fn init() {
@@ -1,13 +1,17 @@
 //! Implementation of [`PageTableEntry`] and [`PageTable`].
 
-use super::{frame_alloc, FrameTracker, PhysPageNum, StepByOne, VirtAddr, VirtPageNum};
+use super::{frame_alloc, frame_alloc_batch, frame_alloc_contiguous, FrameTracker, PhysAddr, PhysPageNum, StepByOne, VirtAddr, VirtPageNum};
+use crate::config::PAGE_SIZE;
+use alloc::collections::BTreeMap;
+use alloc::string::String;
 use alloc::vec;
 use alloc::vec::Vec;
 use bitflags::*;
+use core::fmt::{self, Debug, Display, Formatter};
 
 bitflags! {
     /// page table entry flags
-    pub struct PTEFlags: u8 {
+    pub struct PTEFlags: u16 {
         const V = 1 << 0;
         const R = 1 << 1;
         const W = 1 << 2;
@@ -16,6 +20,97 @@ bitflags! {
         const G = 1 << 5;
         const A = 1 << 6;
         const D = 1 << 7;
+        /// software bit (RSW): a write-protected page shared copy-on-write
+        const COW = 1 << 8;
+    }
+}
+
+impl PTEFlags {
+    /// Convert the `port` of mmap/mprotect (bit 0: R, bit 1: W, bit 2: X) to
+    /// flags of a user page; `U` is always set, and `V` is added by `PageTable::map`.
+    pub fn from_port(port: usize) -> Self {
+        let mut flags = PTEFlags::U;
+        if port & (1 << 0) != 0 {
+            flags |= PTEFlags::R;
+        }
+        if port & (1 << 1) != 0 {
+            flags |= PTEFlags::W;
+        }
+        if port & (1 << 2) != 0 {
+            flags |= PTEFlags::X;
+        }
+        flags
+    }
+    /// The inverse of [`PTEFlags::from_port`], keeping only the R/W/X bits.
+    pub fn to_port(&self) -> usize {
+        let mut port = 0;
+        if self.contains(PTEFlags::R) {
+            port |= 1 << 0;
+        }
+        if self.contains(PTEFlags::W) {
+            port |= 1 << 1;
+        }
+        if self.contains(PTEFlags::X) {
+            port |= 1 << 2;
+        }
+        port
+    }
+}
+
+/// The hardware bits as letters in the order `VRWXUGAD`, `-` for a clear bit,
+/// followed by `+COW` for a copy-on-write page, e.g. `VRW-U---`.
+impl Display for PTEFlags {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        let letters = [
+            (PTEFlags::V, 'V'),
+            (PTEFlags::R, 'R'),
+            (PTEFlags::W, 'W'),
+            (PTEFlags::X, 'X'),
+            (PTEFlags::U, 'U'),
+            (PTEFlags::G, 'G'),
+            (PTEFlags::A, 'A'),
+            (PTEFlags::D, 'D'),
+        ];
+        for (flag, letter) in letters {
+            f.write_fmt(format_args!("{}", if self.contains(flag) { letter } else { '-' }))?;
+        }
+        if self.contains(PTEFlags::COW) {
+            f.write_str("+COW")?;
+        }
+        Ok(())
+    }
+}
+
+/// The `port` of mmap/mprotect, bit 0: R, bit 1: W, bit 2: X.
+///
+/// A value always grants at least one permission and has no reserved bit set.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct MmapPort(u8);
+
+impl MmapPort {
+    /// `None` if any reserved bit is set or no permission is given.
+    pub fn from_bits(bits: u8) -> Option<Self> {
+        if bits & !0x7 != 0 || bits & 0x7 == 0 {
+            None
+        } else {
+            Some(Self(bits))
+        }
+    }
+    pub fn bits(&self) -> u8 {
+        self.0
+    }
+    /// Flags of a user page with this permission, see [`PTEFlags::from_port`].
+    ///
+    /// Executable pages are made readable as well, so execute-only and
+    /// write-execute ports still give a legal leaf encoding. Write-only stays
+    /// as it is: the ch4_mmap2 test relies on accessing such a page to fault.
+    pub fn to_pte_flags(self) -> PTEFlags {
+        let flags = PTEFlags::from_port(self.0 as usize);
+        if flags.contains(PTEFlags::X) {
+            flags | PTEFlags::R
+        } else {
+            flags
+        }
     }
 }
 
@@ -38,12 +133,21 @@ impl PageTableEntry {
     pub fn ppn(&self) -> PhysPageNum {
         (self.bits >> 10 & ((1usize << 44) - 1)).into()
     }
+    /// The flag bits, ignoring software bits that have no flag defined.
     pub fn flags(&self) -> PTEFlags {
-        PTEFlags::from_bits(self.bits as u8).unwrap()
+        PTEFlags::from_bits_truncate((self.bits & ((1usize << 10) - 1)) as u16)
+    }
+    /// The two bits 8-9 reserved for software (RSW); bit 8 is used for `PTEFlags::COW`.
+    pub fn software_bits(&self) -> usize {
+        self.bits >> 8 & 0b11
     }
     pub fn is_valid(&self) -> bool {
         (self.flags() & PTEFlags::V) != PTEFlags::empty()
     }
+    /// A valid entry with any of R/W/X set maps memory, others point to the next level.
+    pub fn is_leaf(&self) -> bool {
+        self.is_valid() && self.flags().intersects(PTEFlags::R | PTEFlags::W | PTEFlags::X)
+    }
     pub fn readable(&self) -> bool {
         (self.flags() & PTEFlags::R) != PTEFlags::empty()
     }
@@ -53,6 +157,55 @@ impl PageTableEntry {
     pub fn executable(&self) -> bool {
         (self.flags() & PTEFlags::X) != PTEFlags::empty()
     }
+    pub fn accessed(&self) -> bool {
+        (self.flags() & PTEFlags::A) != PTEFlags::empty()
+    }
+    pub fn dirty(&self) -> bool {
+        (self.flags() & PTEFlags::D) != PTEFlags::empty()
+    }
+    /// Clear the accessed and dirty bits.
+    pub fn clear_ad(&mut self) {
+        self.bits &= !((PTEFlags::A | PTEFlags::D).bits as usize);
+    }
+}
+
+/// Debugging
+impl Debug for PageTableEntry {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.write_fmt(format_args!("PTE {{ ppn: {:#x}, flags: {} }}", self.ppn().0, self.flags()))
+    }
+}
+
+/// Flush the TLB entry of `vpn` after its PTE has been changed.
+///
+/// The whole TLB is flushed whenever `satp` is switched, so this only matters for
+/// the active page table, but it is harmless otherwise. Page tables built by
+/// `PageTable::from_token` only read PTEs to translate syscall arguments and
+/// never need it.
+pub fn flush_tlb(vpn: VirtPageNum) {
+    let va: VirtAddr = vpn.into();
+    unsafe {
+        core::arch::asm!("sfence.vma {}, zero", in(reg) usize::from(va));
+    }
+}
+
+/// Flush the whole TLB, as needed after switching `satp`. Changes to single
+/// pages only need [`flush_tlb`].
+pub fn flush_tlb_all() {
+    unsafe {
+        core::arch::asm!("sfence.vma");
+    }
+}
+
+/// Outcome of looking up the leaf PTE of a page, see [`PageTable::lookup`].
+pub enum LookupResult<'a> {
+    /// a table on the way to the leaf is missing, so the page has never been
+    /// mapped or all pages around it have been unmapped again
+    NoPath,
+    /// the leaf table exists but the entry of the page is not valid
+    InvalidLeaf(&'a PageTableEntry),
+    /// the page is mapped; inside a megapage this is the middle-level entry
+    Valid(&'a PageTableEntry),
 }
 
 /// page table structure
@@ -61,14 +214,16 @@ pub struct PageTable {
     frames: Vec<FrameTracker>,
 }
 
-/// Assume that it won't oom when creating/mapping.
+/// Running out of frames is reported by `new`, `try_map` and `map_range`;
+/// `map` assumes that it won't oom.
 impl PageTable {
-    pub fn new() -> Self {
-        let frame = frame_alloc().unwrap();
-        PageTable {
+    /// Create an empty page table, or `None` if no frame is left for the root.
+    pub fn new() -> Option<Self> {
+        let frame = frame_alloc()?;
+        Some(PageTable {
             root_ppn: frame.ppn,
             frames: vec![frame],
-        }
+        })
     }
     /// Temporarily used to get arguments from user space.
     pub fn from_token(satp: usize) -> Self {
@@ -77,36 +232,82 @@ impl PageTable {
             frames: Vec::new(),
         }
     }
+    /// Walk to the leaf PTE of `vpn`, creating the missing intermediate tables.
+    /// Returns `None` if frames run out, leaving no newly created table behind.
     fn find_pte_create(&mut self, vpn: VirtPageNum) -> Option<&mut PageTableEntry> {
         let mut idxs = vpn.indexes();
         let mut ppn = self.root_ppn;
         let mut result: Option<&mut PageTableEntry> = None;
+        // the root entry of a level-1 table created in this walk
+        let mut created: Option<&mut PageTableEntry> = None;
         for (i, idx) in idxs.iter_mut().enumerate() {
             let pte = &mut ppn.get_pte_array()[*idx];
             if i == 2 {
                 result = Some(pte);
                 break;
             }
+            assert!(!pte.is_leaf(), "vpn {:?} lies in a megapage", vpn);
             if !pte.is_valid() {
-                let frame = frame_alloc().unwrap();
+                let frame = match frame_alloc() {
+                    Some(frame) => frame,
+                    None => {
+                        if let Some(root_pte) = created {
+                            *root_pte = PageTableEntry::empty();
+                            self.frames.pop();
+                        }
+                        return None;
+                    }
+                };
                 *pte = PageTableEntry::new(frame.ppn, PTEFlags::V);
                 self.frames.push(frame);
+                if i == 0 {
+                    ppn = pte.ppn();
+                    created = Some(pte);
+                    continue;
+                }
             }
             ppn = pte.ppn();
         }
         result
     }
-    fn find_pte(&self, vpn: VirtPageNum) -> Option<&PageTableEntry> {
+    /// Walk to the leaf PTE of `vpn` together with its level: 2 for a normal
+    /// page, or the level of a leaf found earlier, such as 1 for a megapage.
+    fn find_leaf(&self, vpn: VirtPageNum) -> Option<(&PageTableEntry, usize)> {
         let idxs = vpn.indexes();
         let mut ppn = self.root_ppn;
-        let mut result: Option<&PageTableEntry> = None;
         for (i, idx) in idxs.iter().enumerate() {
             let pte = &ppn.get_pte_array()[*idx];
+            if i == 2 || pte.is_leaf() {
+                return Some((pte, i));
+            }
+            if !pte.is_valid() {
+                return None;
+            }
+            ppn = pte.ppn();
+        }
+        None
+    }
+    /// Look up the leaf PTE of `vpn`, telling a missing path apart from an invalid leaf.
+    pub fn lookup(&self, vpn: VirtPageNum) -> LookupResult<'_> {
+        match self.find_leaf(vpn) {
+            None => LookupResult::NoPath,
+            Some((pte, _)) if pte.is_valid() => LookupResult::Valid(pte),
+            Some((pte, _)) => LookupResult::InvalidLeaf(pte),
+        }
+    }
+    /// Walk to the last-level PTE of `vpn`. A megapage can't be changed page by
+    /// page, so `None` is returned for a vpn inside one.
+    fn find_pte_mut(&mut self, vpn: VirtPageNum) -> Option<&mut PageTableEntry> {
+        let idxs = vpn.indexes();
+        let mut ppn = self.root_ppn;
+        let mut result: Option<&mut PageTableEntry> = None;
+        for (i, idx) in idxs.iter().enumerate() {
+            let pte = &mut ppn.get_pte_array()[*idx];
             if i == 2 {
                 result = Some(pte);
                 break;
             }
-            if !pte.is_valid() {
+            if !pte.is_valid() || pte.is_leaf() {
                 return None;
             }
             ppn = pte.ppn();
@@ -115,57 +316,684 @@ impl PageTable {
     }
     #[allow(unused)]
     pub fn map(&mut self, vpn: VirtPageNum, ppn: PhysPageNum, flags: PTEFlags) {
-        let pte = self.find_pte_create(vpn).unwrap();
+        self.try_map(vpn, ppn, flags).unwrap();
+    }
+    /// Like `map`, but returns `Err` without changing anything if frames for
+    /// the intermediate tables run out.
+    pub fn try_map(&mut self, vpn: VirtPageNum, ppn: PhysPageNum, flags: PTEFlags) -> Result<(), ()> {
+        let pte = self.find_pte_create(vpn).ok_or(())?;
         assert!(!pte.is_valid(), "vpn {:?} is mapped before mapping", vpn);
         *pte = PageTableEntry::new(ppn, flags | PTEFlags::V);
+        flush_tlb(vpn);
+        Ok(())
+    }
+    /// Map the 2MiB region starting at `vpn` to the one at `ppn` with a single
+    /// leaf entry in the middle level, a megapage. Both must be 2MiB-aligned and
+    /// no page of the region may be mapped yet.
+    ///
+    /// Megapages are only translated; `unmap`, `set_flags` and the like treat
+    /// their pages as not mapped.
+    ///
+    /// Returns `Err` without changing anything if there is no frame for the middle-level table.
+    #[allow(unused)]
+    pub fn map_huge(&mut self, vpn: VirtPageNum, ppn: PhysPageNum, flags: PTEFlags) -> Result<(), ()> {
+        assert!(
+            vpn.0 % 512 == 0 && ppn.0 % 512 == 0,
+            "megapage {:?} -> {:?} is not 2MiB-aligned",
+            vpn,
+            ppn
+        );
+        let idxs = vpn.indexes();
+        let root_pte = &mut self.root_ppn.get_pte_array()[idxs[0]];
+        assert!(!root_pte.is_leaf(), "vpn {:?} lies in a gigapage", vpn);
+        if !root_pte.is_valid() {
+            let frame = frame_alloc().ok_or(())?;
+            *root_pte = PageTableEntry::new(frame.ppn, PTEFlags::V);
+            self.frames.push(frame);
+        }
+        let pte = &mut root_pte.ppn().get_pte_array()[idxs[1]];
+        assert!(!pte.is_valid(), "vpn {:?} is mapped before mapping a megapage", vpn);
+        *pte = PageTableEntry::new(ppn, flags | PTEFlags::V);
+        flush_tlb(vpn);
+        Ok(())
     }
+    /// Unmap `vpn` and reclaim the page-table frames left empty by it.
+    ///
+    /// Data frames are owned by the caller (see `MapArea::data_frames`) and are
+    /// not touched here; only the structural frames in `self.frames` are freed.
     #[allow(unused)]
     pub fn unmap(&mut self, vpn: VirtPageNum) {
-        let pte = self.find_pte_create(vpn).unwrap();
-        assert!(pte.is_valid(), "vpn {:?} is invalid before unmapping", vpn);
+        match self.lookup(vpn) {
+            LookupResult::NoPath => panic!("vpn {:?} has no page table before unmapping", vpn),
+            LookupResult::InvalidLeaf(_) => panic!("vpn {:?} is invalid before unmapping", vpn),
+            LookupResult::Valid(_) => {
+                if self.try_unmap(vpn).is_err() {
+                    panic!("vpn {:?} lies in a megapage", vpn);
+                }
+            }
+        }
+    }
+    /// Like `unmap`, but returns `Err` if `vpn` is not mapped. A missing
+    /// intermediate table means the page is not mapped; nothing is allocated.
+    pub fn try_unmap(&mut self, vpn: VirtPageNum) -> Result<(), ()> {
+        let idxs = vpn.indexes();
+        let mut tables = [self.root_ppn; 3];
+        for i in 1..3 {
+            let pte = &tables[i - 1].get_pte_array()[idxs[i - 1]];
+            if !pte.is_valid() || pte.is_leaf() {
+                return Err(());
+            }
+            tables[i] = pte.ppn();
+        }
+        let pte = &mut tables[2].get_pte_array()[idxs[2]];
+        if !pte.is_valid() {
+            return Err(());
+        }
         *pte = PageTableEntry::empty();
+        flush_tlb(vpn);
+        for i in (1..3).rev() {
+            if tables[i].get_pte_array().iter().any(|pte| pte.is_valid()) {
+                break;
+            }
+            tables[i - 1].get_pte_array()[idxs[i - 1]] = PageTableEntry::empty();
+            self.frames.retain(|frame| frame.ppn != tables[i]);
+        }
+        Ok(())
+    }
+    /// Change the flags of a mapped page in place, keeping its frame.
+    /// Returns `Err` if `vpn` is not mapped.
+    pub fn set_flags(&mut self, vpn: VirtPageNum, flags: PTEFlags) -> Result<(), ()> {
+        match self.find_pte_mut(vpn) {
+            Some(pte) if pte.is_valid() => {
+                *pte = PageTableEntry::new(pte.ppn(), flags | PTEFlags::V);
+            }
+            _ => return Err(()),
+        }
+        flush_tlb(vpn);
+        Ok(())
+    }
+    /// Point a mapped page to another frame with new flags, in place.
+    /// Returns `Err` if `vpn` is not mapped.
+    pub fn remap(&mut self, vpn: VirtPageNum, ppn: PhysPageNum, flags: PTEFlags) -> Result<(), ()> {
+        match self.find_pte_mut(vpn) {
+            Some(pte) if pte.is_valid() => {
+                *pte = PageTableEntry::new(ppn, flags | PTEFlags::V);
+            }
+            _ => return Err(()),
+        }
+        flush_tlb(vpn);
+        Ok(())
+    }
+    /// Share the page `vpn` with `child` copy-on-write: a writable page becomes
+    /// read-only with the `COW` bit in both page tables, other pages are shared as is.
+    /// Returns `Err`, changing neither table, if frames for the child's tables run out.
+    pub fn share_cow(&mut self, child: &mut PageTable, vpn: VirtPageNum) -> Result<(), ()> {
+        let pte = self.translate(vpn).unwrap();
+        let mut flags = pte.flags();
+        if flags.contains(PTEFlags::W) {
+            flags.remove(PTEFlags::W);
+            flags.insert(PTEFlags::COW);
+        }
+        child.try_map(vpn, pte.ppn(), flags)?;
+        if flags != pte.flags() {
+            self.set_flags(vpn, flags).unwrap();
+        }
+        Ok(())
+    }
+    /// Clear the accessed bit of a mapped page and flush its TLB entry, so the
+    /// next access by the hardware sets it again. Returns `Err` if `vpn` is not mapped.
+    pub fn clear_accessed(&mut self, vpn: VirtPageNum) -> Result<(), ()> {
+        match self.find_pte_mut(vpn) {
+            Some(pte) if pte.is_valid() => {
+                pte.bits &= !(PTEFlags::A.bits as usize);
+            }
+            _ => return Err(()),
+        }
+        flush_tlb(vpn);
+        Ok(())
+    }
+    /// Clear the accessed and dirty bits of a mapped page and flush its TLB entry,
+    /// so the hardware sets them again on the next access. Returns `Err` if `vpn` is not mapped.
+    pub fn clear_refs(&mut self, vpn: VirtPageNum) -> Result<(), ()> {
+        match self.find_pte_mut(vpn) {
+            Some(pte) if pte.is_valid() => pte.clear_ad(),
+            _ => return Err(()),
+        }
+        flush_tlb(vpn);
+        Ok(())
+    }
+    /// Map every page in `[start, end)` to a freshly allocated frame.
+    ///
+    /// The frames are allocated as one batch and returned so that the address-space
+    /// layer owns them and frees them on unmap. If frames run out, the pages mapped
+    /// so far are unmapped again and `Err` is returned.
+    pub fn map_range(
+        &mut self,
+        start: VirtPageNum,
+        end: VirtPageNum,
+        flags: PTEFlags,
+    ) -> Result<BTreeMap<VirtPageNum, FrameTracker>, ()> {
+        let batch = frame_alloc_batch(end.0.saturating_sub(start.0)).ok_or(())?;
+        let mut frames = BTreeMap::new();
+        let mut vpn = start;
+        for frame in batch {
+            // the tables on the way may still run out of frames
+            if self.try_map(vpn, frame.ppn, flags).is_err() {
+                self.unmap_range(start, vpn).unwrap();
+                return Err(());
+            }
+            frames.insert(vpn, frame);
+            vpn.step();
+        }
+        Ok(frames)
     }
+    /// Unmap every page in `[start, end)`; the caller drops the data frames.
+    /// Returns `Err` without unmapping anything if some page is not mapped.
+    pub fn unmap_range(&mut self, start: VirtPageNum, end: VirtPageNum) -> Result<(), ()> {
+        let mut vpn = start;
+        while vpn < end {
+            if !self.translate(vpn).map_or(false, |pte| pte.is_valid()) {
+                return Err(());
+            }
+            vpn.step();
+        }
+        let mut vpn = start;
+        while vpn < end {
+            self.unmap(vpn);
+            vpn.step();
+        }
+        Ok(())
+    }
+    /// The leaf PTE of `vpn`. Inside a megapage this is an entry made up for
+    /// the 4KiB page of `vpn`, with the flags of the megapage.
     pub fn translate(&self, vpn: VirtPageNum) -> Option<PageTableEntry> {
-        self.find_pte(vpn).copied()
+        self.find_leaf(vpn).map(|(pte, level)| {
+            if level == 2 {
+                return *pte;
+            }
+            let offset = vpn.0 & ((1usize << (9 * (2 - level))) - 1);
+            PageTableEntry::new(PhysPageNum(pte.ppn().0 + offset), pte.flags())
+        })
+    }
+    /// Every valid leaf mapping in increasing order of vpn.
+    ///
+    /// This only reads the table and allocates nothing, so it is safe to use
+    /// while panicking.
+    pub fn iter_mappings(&self) -> impl Iterator<Item = (VirtPageNum, PhysPageNum, PTEFlags)> {
+        fn valid_entries(table: PhysPageNum) -> impl Iterator<Item = (usize, &'static PageTableEntry)> {
+            let ptes: &'static [PageTableEntry] = table.get_pte_array();
+            ptes.iter().enumerate().filter(|(_, pte)| pte.is_valid())
+        }
+        valid_entries(self.root_ppn).flat_map(|(i0, pte0)| {
+            valid_entries(pte0.ppn()).flat_map(move |(i1, pte1)| {
+                // a megapage is listed page by page
+                (0..512).filter_map(move |i2| {
+                    let vpn = VirtPageNum((i0 << 18) | (i1 << 9) | i2);
+                    if pte1.is_leaf() {
+                        return Some((vpn, PhysPageNum(pte1.ppn().0 + i2), pte1.flags()));
+                    }
+                    let pte2 = &pte1.ppn().get_pte_array()[i2];
+                    if pte2.is_valid() {
+                        Some((vpn, pte2.ppn(), pte2.flags()))
+                    } else {
+                        None
+                    }
+                })
+            })
+        })
+    }
+    /// Print every valid leaf mapping at debug level.
+    #[allow(unused)]
+    pub fn dump(&self) {
+        debug!("page table {:?}:", self.root_ppn);
+        for (vpn, ppn, flags) in self.iter_mappings() {
+            debug!("  {:?} -> {:?} {}", vpn, ppn, flags);
+        }
+    }
+    /// Print the mapped regions at debug level, coalescing runs of contiguous
+    /// pages with identical flags into one line.
+    #[allow(unused)]
+    pub fn dump_mappings(&self) {
+        debug!("page table {:?}:", self.root_ppn);
+        // (first vpn, page count, flags) of the run being built
+        let mut run: Option<(VirtPageNum, usize, PTEFlags)> = None;
+        for (vpn, _, flags) in self.iter_mappings() {
+            match run {
+                Some((start, count, run_flags)) if start.0 + count == vpn.0 && run_flags == flags => {
+                    run = Some((start, count + 1, run_flags));
+                }
+                _ => {
+                    if let Some((start, count, run_flags)) = run {
+                        log_region(start, count, run_flags);
+                    }
+                    run = Some((vpn, 1, flags));
+                }
+            }
+        }
+        if let Some((start, count, run_flags)) = run {
+            log_region(start, count, run_flags);
+        }
+    }
+    /// Translate a virtual address to its physical address, keeping the page offset.
+    /// Returns `None` if the page is not mapped.
+    pub fn translate_va(&self, va: VirtAddr) -> Option<PhysAddr> {
+        self.translate(va.floor())
+            .filter(|pte| pte.is_valid())
+            .map(|pte| {
+                let aligned_pa: PhysAddr = pte.ppn().into();
+                PhysAddr::from(aligned_pa.0 + va.page_offset())
+            })
     }
     pub fn token(&self) -> usize {
         8usize << 60 | self.root_ppn.0
     }
 }
 
-/// translate a pointer to a mutable u8 Vec through page table
-pub fn translated_byte_buffer(token: usize, ptr: *const u8, len: usize) -> Vec<&'static mut [u8]> {
-    let page_table = PageTable::from_token(token);
-    let mut start = ptr as usize;
-    let end = start + len;
-    let mut v = Vec::new();
-    while start < end {
-        let start_va = VirtAddr::from(start);
-        let mut vpn = start_va.floor();
-        let ppn = page_table.translate(vpn).unwrap().ppn();
-        vpn.step();
-        let mut end_va: VirtAddr = vpn.into();
-        end_va = end_va.min(VirtAddr::from(end));
-        if end_va.page_offset() == 0 {
-            v.push(&mut ppn.get_bytes_array()[start_va.page_offset()..]);
-        } else {
-            v.push(&mut ppn.get_bytes_array()[start_va.page_offset()..end_va.page_offset()]);
+/// Access to the user address space of `token` for the duration of one syscall.
+///
+/// The page table wrapper is built once and shared by every read and write; each
+/// of them checks that all pages involved are valid, user-accessible and carry
+/// the needed permission before touching any of them.
+pub struct UserPtrReader {
+    page_table: PageTable,
+    token: usize,
+}
+
+impl UserPtrReader {
+    pub fn new(token: usize) -> Self {
+        Self {
+            page_table: PageTable::from_token(token),
+            token,
+        }
+    }
+    pub fn token(&self) -> usize {
+        self.token
+    }
+    /// Whether every page of `[va, va + len)` is readable, or writable if `need_write`.
+    pub fn check(&self, va: usize, len: usize, need_write: bool) -> bool {
+        let flags = if need_write { PTEFlags::W } else { PTEFlags::R };
+        self.buffer(va, len, flags).is_ok()
+    }
+    /// Fill `dst` from user space at `src_va`, `dst` is untouched on `Err`.
+    pub fn read_bytes(&self, src_va: usize, dst: &mut [u8]) -> Result<(), ()> {
+        let mut copied = 0;
+        for src in self.buffer(src_va, dst.len(), PTEFlags::R)? {
+            dst[copied..copied + src.len()].copy_from_slice(src);
+            copied += src.len();
+        }
+        Ok(())
+    }
+    /// Copy `src` to user space at `dst_va`, nothing is written on `Err`.
+    pub fn write_bytes(&self, dst_va: usize, src: &[u8]) -> Result<(), ()> {
+        let mut copied = 0;
+        for dst in self.buffer(dst_va, src.len(), PTEFlags::W)? {
+            dst.copy_from_slice(&src[copied..copied + dst.len()]);
+            copied += dst.len();
+        }
+        Ok(())
+    }
+    /// Read a `T` at `src_va` into `value`, which may span several pages.
+    pub fn read_struct<T>(&self, src_va: usize, value: &mut T) -> Result<(), ()> {
+        let dst = unsafe {
+            core::slice::from_raw_parts_mut(value as *mut T as *mut u8, core::mem::size_of::<T>())
+        };
+        self.read_bytes(src_va, dst)
+    }
+    /// Write `value` to `dst_va` byte by byte, it may span several pages.
+    pub fn write_struct<T>(&self, dst_va: usize, value: &T) -> Result<(), ()> {
+        let src = unsafe {
+            core::slice::from_raw_parts(value as *const T as *const u8, core::mem::size_of::<T>())
+        };
+        self.write_bytes(dst_va, src)
+    }
+    /// Read a null-terminated string, which may span several pages.
+    ///
+    /// Returns `Err` if the string runs into a page that is unmapped or not user-readable.
+    pub fn read_str(&self, va: usize) -> Result<String, ()> {
+        let mut string = String::new();
+        let mut va = va;
+        loop {
+            // the rest of the page that va lies in
+            let rest = PAGE_SIZE - VirtAddr::from(va).page_offset();
+            let bytes = self.buffer(va, rest, PTEFlags::R)?.pop().unwrap();
+            for &ch in bytes.iter() {
+                if ch == 0 {
+                    return Ok(string);
+                }
+                string.push(ch as char);
+            }
+            va += bytes.len();
+        }
+    }
+    /// 逐页翻译用户空间区域 [user_va, user_va + len)，要求每一页都有效、用户可访问且具备 flags 权限
+    /// 任意一页不满足时返回 Err，此时不会对任何一页进行读写
+    fn buffer(
+        &self,
+        user_va: usize,
+        len: usize,
+        flags: PTEFlags,
+    ) -> Result<Vec<&'static mut [u8]>, ()> {
+        let mut start = user_va;
+        let end = start.checked_add(len).ok_or(())?;
+        let mut v = Vec::new();
+        while start < end {
+            let start_va = VirtAddr::from(start);
+            let mut vpn = start_va.floor();
+            let pte = self.page_table.translate(vpn).ok_or(())?;
+            if !pte.is_valid() || !pte.flags().contains(flags | PTEFlags::U) {
+                return Err(());
+            }
+            let ppn = pte.ppn();
+            vpn.step();
+            let mut end_va: VirtAddr = vpn.into();
+            end_va = end_va.min(VirtAddr::from(end));
+            let n = usize::from(end_va) - start;
+            let offset = start_va.page_offset();
+            v.push(ppn.get_bytes_range(offset, offset + n).ok_or(())?);
+            start = end_va.into();
         }
-        start = end_va.into();
+        Ok(v)
     }
-    v
+}
+
+/// translate a pointer to a mutable u8 Vec through page table
+///
+/// Returns `None` if any page in the range is unmapped or not accessible from user mode.
+pub fn translated_byte_buffer(
+    token: usize,
+    ptr: *const u8,
+    len: usize,
+) -> Option<Vec<&'static mut [u8]>> {
+    UserPtrReader::new(token)
+        .buffer(ptr as usize, len, PTEFlags::empty())
+        .ok()
+}
+
+/// 检查用户空间区域 [va, va + len) 的每一页都有效、用户可访问且可读，need_write 时还要求可写
+/// 写时复制的页在解除共享之前不可写
+pub fn check_user_ptr(token: usize, va: usize, len: usize, need_write: bool) -> bool {
+    UserPtrReader::new(token).check(va, len, need_write)
+}
+
+/// Print one region of [`PageTable::dump_mappings`].
+fn log_region(start: VirtPageNum, count: usize, flags: PTEFlags) {
+    let start_va: VirtAddr = start.into();
+    let end_va: VirtAddr = VirtPageNum(start.0 + count).into();
+    debug!(
+        "  [{:#x}, {:#x}) {} pages {}",
+        start_va.0, end_va.0, count, flags
+    );
 }
 
 /// 复制内核空间地址数据到用户空间地址
 /// 参数 -- token: 用户地址空间token，dst_user_va：用户空间目标地址，内核空间源数据地址，len：数据字节长度
-pub fn copy_kernel_to_user(token: usize, kernel_src_va: *const u8, user_dst_va: usize, len: usize) {
+/// 用户空间目标地址未映射或不可写时返回 Err
+pub fn copy_kernel_to_user(
+    token: usize,
+    kernel_src_va: *const u8,
+    user_dst_va: usize,
+    len: usize,
+) -> Result<(), ()> {
     // 用户空间采用Framed映射，内核空间采用恒等映射，所以只需要翻译用户空间地址
+    let src = unsafe { core::slice::from_raw_parts(kernel_src_va, len) };
+    UserPtrReader::new(token).write_bytes(user_dst_va, src)
+}
+
+/// 将任意类型的内核对象按字节复制到用户空间地址，对象可以跨越多个页
+pub fn copy_type_into_user<T>(token: usize, value: &T, dst_va: usize) -> Result<(), ()> {
+    UserPtrReader::new(token).write_struct(dst_va, value)
+}
+
+/// 复制用户空间地址数据到内核空间地址，是 copy_kernel_to_user 的逆操作
+/// 参数 -- token: 用户地址空间token，kernel_dst_va：内核空间目标地址，user_src_va：用户空间源数据地址，len：数据字节长度
+/// 用户空间源地址未映射或不可读时返回 Err，此时目标地址不会被写入
+pub fn copy_user_to_kernel(
+    token: usize,
+    kernel_dst_va: *mut u8,
+    user_src_va: usize,
+    len: usize,
+) -> Result<(), ()> {
+    let dst = unsafe { core::slice::from_raw_parts_mut(kernel_dst_va, len) };
+    UserPtrReader::new(token).read_bytes(user_src_va, dst)
+}
+
+/// 将用户空间地址处的结构体整体读入内核对象 value，结构体可以跨越多个页
+/// 读取失败时 value 保持不变
+pub fn copy_type_from_user<T>(token: usize, src_va: usize, value: &mut T) -> Result<(), ()> {
+    UserPtrReader::new(token).read_struct(src_va, value)
+}
+
+/// translate a null-terminated string from user space, which may span several pages
+///
+/// Returns `Err` if the string runs into a page that is unmapped or not user-readable.
+pub fn translated_str(token: usize, ptr: *const u8) -> Result<String, ()> {
+    UserPtrReader::new(token).read_str(ptr as usize)
+}
+
+/// translate a user pointer to an immutable reference of `T`
+///
+/// `T` must fit in the page `ptr` points into; objects crossing a page boundary
+/// are not physically contiguous and cause a panic, use `copy_user_to_kernel` for them instead.
+pub fn translated_ref<T>(token: usize, ptr: *const T) -> &'static T {
+    translated_refmut(token, ptr as *mut T)
+}
+
+/// translate a user pointer to a mutable reference of `T`
+///
+/// `T` must fit in the page `ptr` points into; objects crossing a page boundary
+/// are not physically contiguous and cause a panic, use `copy_kernel_to_user` for them instead.
+pub fn translated_refmut<T>(token: usize, ptr: *mut T) -> &'static mut T {
     let page_table = PageTable::from_token(token);
-    let user_start_va = VirtAddr::from(user_dst_va);
-    let user_start_vpn = user_start_va.floor();
-    let user_start_ppn = page_table.translate(user_start_vpn).unwrap().ppn();
-    let user_dst_pa = &mut user_start_ppn.get_bytes_array()[user_start_va.page_offset()..user_start_va.page_offset() + len];
-    unsafe {
-        user_dst_pa.copy_from_slice(core::slice::from_raw_parts(kernel_src_va, len));
+    let va = VirtAddr::from(ptr as usize);
+    let offset = va.page_offset();
+    assert!(
+        offset + core::mem::size_of::<T>() <= PAGE_SIZE,
+        "{:?} with size {} crosses a page boundary",
+        va,
+        core::mem::size_of::<T>()
+    );
+    page_table.translate_va(va).unwrap().get_mut()
+}
+
+#[allow(unused)]
+/// map and unmap page ranges, including empty ones
+pub fn map_range_test() {
+    let mut page_table = PageTable::new().unwrap();
+    let start = VirtPageNum(0x10000);
+    let frames = page_table.map_range(start, start, PTEFlags::R).unwrap();
+    assert!(frames.is_empty());
+    assert!(page_table.translate(start).is_none());
+    page_table.unmap_range(start, start).unwrap();
+    let end = VirtPageNum(start.0 + 3);
+    let frames = page_table.map_range(start, end, PTEFlags::R | PTEFlags::W).unwrap();
+    assert_eq!(frames.len(), 3);
+    for (vpn, frame) in frames.iter() {
+        let pte = page_table.translate(*vpn).unwrap();
+        assert!(pte.is_valid() && pte.writable());
+        assert_eq!(pte.ppn(), frame.ppn);
+    }
+    let va = VirtAddr::from(VirtAddr::from(start).0 + 0x123);
+    let pa = PhysAddr::from(PhysAddr::from(frames[&start].ppn).0 + 0x123);
+    assert_eq!(page_table.translate_va(va), Some(pa));
+    assert_eq!(page_table.translate_va(end.into()), None);
+    page_table.set_flags(start, PTEFlags::R).unwrap();
+    let pte = page_table.translate(start).unwrap();
+    assert!(pte.is_valid() && !pte.writable());
+    assert_eq!(pte.ppn(), frames[&start].ppn);
+    assert!(page_table.set_flags(end, PTEFlags::R).is_err());
+    // nothing is unmapped if a page in the range is missing
+    assert!(page_table.unmap_range(start, VirtPageNum(end.0 + 1)).is_err());
+    assert!(page_table.translate(start).unwrap().is_valid());
+    page_table.unmap_range(start, end).unwrap();
+    assert!(page_table.translate(start).is_none());
+    assert!(page_table.set_flags(start, PTEFlags::R).is_err());
+    info!("map_range_test passed!");
+}
+
+#[allow(unused)]
+/// convert every port combination to flags and back
+pub fn port_flags_test() {
+    for port in 0..8 {
+        let flags = PTEFlags::from_port(port);
+        assert!(flags.contains(PTEFlags::U));
+        assert!(!flags.intersects(PTEFlags::V | PTEFlags::G | PTEFlags::A | PTEFlags::D));
+        assert_eq!(flags.contains(PTEFlags::R), port & 1 != 0);
+        assert_eq!(flags.contains(PTEFlags::W), port & 2 != 0);
+        assert_eq!(flags.contains(PTEFlags::X), port & 4 != 0);
+        assert_eq!(flags.to_port(), port);
+        match MmapPort::from_bits(port as u8) {
+            Some(mmap_port) if port & 4 != 0 => {
+                assert_eq!(mmap_port.to_pte_flags(), flags | PTEFlags::R)
+            }
+            Some(mmap_port) => assert_eq!(mmap_port.to_pte_flags(), flags),
+            None => assert_eq!(port, 0),
+        }
+    }
+    // reserved bits
+    assert!(MmapPort::from_bits(0x8).is_none());
+    assert!(MmapPort::from_bits(0x8 | 0x3).is_none());
+    assert!(MmapPort::from_bits(0x80 | 0x1).is_none());
+    assert_eq!(MmapPort::from_bits(0x5).unwrap().bits(), 0x5);
+    // executable implies readable
+    let rx = PTEFlags::U | PTEFlags::R | PTEFlags::X;
+    assert_eq!(MmapPort::from_bits(0b100).unwrap().to_pte_flags(), rx);
+    assert_eq!(MmapPort::from_bits(0b101).unwrap().to_pte_flags(), rx);
+    assert_eq!(MmapPort::from_bits(0b110).unwrap().to_pte_flags(), rx | PTEFlags::W);
+    info!("port_flags_test passed!");
+}
+
+#[allow(unused)]
+/// check that walking to a leaf allocates exactly the missing intermediate tables
+pub fn find_pte_create_test() {
+    let mut page_table = PageTable::new().unwrap();
+    assert_eq!(page_table.frames.len(), 1);
+    let frame = frame_alloc().unwrap();
+    // root + one table of each lower level
+    let vpn = VirtPageNum(0x12345);
+    page_table.map(vpn, frame.ppn, PTEFlags::R);
+    assert_eq!(page_table.frames.len(), 3);
+    // same leaf table
+    page_table.map(VirtPageNum(vpn.0 + 1), frame.ppn, PTEFlags::R);
+    assert_eq!(page_table.frames.len(), 3);
+    // same level-1 table, new leaf table
+    page_table.map(VirtPageNum(vpn.0 + 512), frame.ppn, PTEFlags::R);
+    assert_eq!(page_table.frames.len(), 4);
+    // new level-1 and leaf tables
+    page_table.map(VirtPageNum(vpn.0 + 512 * 512), frame.ppn, PTEFlags::R);
+    assert_eq!(page_table.frames.len(), 6);
+    for vpn in [vpn, VirtPageNum(vpn.0 + 1), VirtPageNum(vpn.0 + 512), VirtPageNum(vpn.0 + 512 * 512)] {
+        assert_eq!(page_table.translate(vpn).unwrap().ppn(), frame.ppn);
+        page_table.unmap(vpn);
+    }
+    // empty tables are reclaimed, only the root is left
+    assert_eq!(page_table.frames.len(), 1);
+    // unmapping where no table has ever been created allocates nothing
+    assert!(matches!(page_table.lookup(vpn), LookupResult::NoPath));
+    assert!(page_table.try_unmap(vpn).is_err());
+    assert_eq!(page_table.frames.len(), 1);
+    page_table.map(vpn, frame.ppn, PTEFlags::R);
+    assert!(matches!(page_table.lookup(vpn), LookupResult::Valid(pte) if pte.ppn() == frame.ppn));
+    assert!(matches!(page_table.lookup(VirtPageNum(vpn.0 + 1)), LookupResult::InvalidLeaf(_)));
+    assert!(page_table.try_unmap(VirtPageNum(vpn.0 + 1)).is_err());
+    assert_eq!(page_table.frames.len(), 3);
+    assert!(page_table.try_unmap(vpn).is_ok());
+    assert_eq!(page_table.frames.len(), 1);
+    info!("find_pte_create_test passed!");
+}
+
+#[allow(unused)]
+/// iterate over the leaf mappings spread over several tables
+pub fn iter_mappings_test() {
+    let mut page_table = PageTable::new().unwrap();
+    assert_eq!(page_table.iter_mappings().count(), 0);
+    let frame = frame_alloc().unwrap();
+    let vpns = [
+        VirtPageNum(0x12345),
+        VirtPageNum(0x12346),
+        VirtPageNum(0x12345 + 512),
+        VirtPageNum(0x12345 + 512 * 512),
+    ];
+    for vpn in vpns {
+        page_table.map(vpn, frame.ppn, PTEFlags::R | PTEFlags::U);
+    }
+    let mut count = 0;
+    for ((vpn, ppn, flags), expected) in page_table.iter_mappings().zip(vpns) {
+        assert_eq!(vpn, expected);
+        assert_eq!(ppn, frame.ppn);
+        assert_eq!(flags, PTEFlags::V | PTEFlags::R | PTEFlags::U);
+        count += 1;
+    }
+    assert_eq!(count, vpns.len());
+    page_table.dump_mappings();
+    for vpn in vpns {
+        page_table.unmap(vpn);
+    }
+    info!("iter_mappings_test passed!");
+}
+
+#[allow(unused)]
+/// map a 2MiB region as one megapage and read bytes of several 4KiB pages through it
+pub fn map_huge_test() {
+    // 1023 contiguous frames always contain a 2MiB-aligned run of 512
+    let frames = frame_alloc_contiguous(1023).unwrap();
+    let first = frames.iter().position(|frame| frame.ppn.0 % 512 == 0).unwrap();
+    let ppn = frames[first].ppn;
+    let mut page_table = PageTable::new().unwrap();
+    let vpn = VirtPageNum(0x12345 * 512);
+    // without a frame for the middle-level table nothing is mapped
+    let mut hog = Vec::new();
+    while let Some(frame) = frame_alloc() {
+        hog.push(frame);
+    }
+    assert!(page_table
+        .map_huge(vpn, ppn, PTEFlags::R | PTEFlags::W | PTEFlags::U)
+        .is_err());
+    assert_eq!(page_table.frames.len(), 1);
+    assert!(page_table.translate(vpn).is_none());
+    drop(hog);
+    page_table
+        .map_huge(vpn, ppn, PTEFlags::R | PTEFlags::W | PTEFlags::U)
+        .unwrap();
+    // the root table and one middle-level table, no last-level table
+    assert_eq!(page_table.frames.len(), 2);
+    for page in [0, 1, 255, 511] {
+        frames[first + page].ppn.get_bytes_array()[page] = page as u8 + 1;
+        let va: VirtAddr = VirtPageNum(vpn.0 + page).into();
+        let pa = page_table.translate_va(VirtAddr::from(va.0 + page)).unwrap();
+        assert_eq!(pa, PhysAddr::from(PhysAddr::from(ppn).0 + page * PAGE_SIZE + page));
+        assert_eq!(*pa.get_ref::<u8>(), page as u8 + 1);
+        let pte = page_table.translate(VirtPageNum(vpn.0 + page)).unwrap();
+        assert_eq!(pte.ppn(), PhysPageNum(ppn.0 + page));
+        assert!(pte.is_valid() && pte.readable() && pte.writable());
+    }
+    assert!(page_table.translate_va(VirtAddr::from(VirtPageNum(vpn.0 + 512))).is_none());
+    assert_eq!(page_table.iter_mappings().count(), 512);
+    // pages of a megapage can't be changed one by one
+    assert!(page_table.set_flags(VirtPageNum(vpn.0 + 1), PTEFlags::R).is_err());
+    assert!(page_table.try_unmap(VirtPageNum(vpn.0 + 1)).is_err());
+    info!("map_huge_test passed!");
+}
+
+#[allow(unused)]
+/// decoded formatting of page table entries and their flags, software bits included
+pub fn pte_debug_test() {
+    use alloc::format;
+    let flags = PTEFlags::V | PTEFlags::R | PTEFlags::W | PTEFlags::U;
+    assert_eq!(format!("{}", flags), "VRW-U---");
+    assert_eq!(format!("{}", PTEFlags::empty()), "--------");
+    assert_eq!(format!("{}", flags | PTEFlags::A | PTEFlags::D), "VRW-U-AD");
+    assert_eq!(format!("{}", PTEFlags::V | PTEFlags::R | PTEFlags::COW), "VR------+COW");
+    let pte = PageTableEntry::new(PhysPageNum(0x80201), flags);
+    assert_eq!(format!("{:?}", pte), "PTE { ppn: 0x80201, flags: VRW-U--- }");
+    assert_eq!(format!("{:?}", PageTableEntry::empty()), "PTE { ppn: 0x0, flags: -------- }");
+    // software bits without a flag are ignored instead of panicking
+    let pte = PageTableEntry {
+        bits: pte.bits | 1 << 9,
     };
-}
\ No newline at end of file
+    assert_eq!(pte.flags(), flags);
+    assert_eq!(pte.software_bits(), 0b10);
+    let pte = PageTableEntry::new(PhysPageNum(0x80201), flags | PTEFlags::COW);
+    assert_eq!(pte.software_bits(), 0b01);
+    assert_eq!(pte.ppn(), PhysPageNum(0x80201));
+    info!("pte_debug_test passed!");
+}
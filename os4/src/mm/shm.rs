@@ -0,0 +1,77 @@
+//! Shared memory segments, whose frames can be mapped by several tasks at once.
+//!
+//! Every attached task holds a reference to each frame of the segment, so the
+//! strong count of the frames tells whether the segment is still in use.
+
+use super::{frame_alloc, FrameTracker};
+use crate::config::PAGE_SIZE;
+use crate::sync::UPSafeCell;
+use alloc::collections::BTreeMap;
+use alloc::sync::Arc;
+use alloc::vec::Vec;
+use lazy_static::*;
+
+/// a shared memory segment
+struct ShmSegment {
+    frames: Vec<Arc<FrameTracker>>,
+    /// whether any task has attached it, segments are only released after that
+    attached: bool,
+}
+
+impl ShmSegment {
+    /// Whether no task holds the frames any longer.
+    fn detached(&self) -> bool {
+        self.attached
+            && self
+                .frames
+                .iter()
+                .all(|frame| Arc::strong_count(frame) == 1)
+    }
+}
+
+lazy_static! {
+    /// shared memory segments indexed by key
+    static ref SHM_TABLE: UPSafeCell<BTreeMap<usize, ShmSegment>> =
+        unsafe { UPSafeCell::new(BTreeMap::new()) };
+}
+
+/// Create the segment `key` of at least `len` bytes, or check that the existing
+/// one is large enough. Returns false if it is too small or frames run out.
+pub fn shm_get(key: usize, len: usize) -> bool {
+    let mut table = SHM_TABLE.exclusive_access();
+    let pages = (len + PAGE_SIZE - 1) / PAGE_SIZE;
+    if let Some(segment) = table.get(&key) {
+        return segment.frames.len() >= pages;
+    }
+    let mut frames = Vec::with_capacity(pages);
+    for _ in 0..pages {
+        match frame_alloc() {
+            Some(frame) => frames.push(Arc::new(frame)),
+            None => return false,
+        }
+    }
+    table.insert(
+        key,
+        ShmSegment {
+            frames,
+            attached: false,
+        },
+    );
+    true
+}
+
+/// References to the frames of segment `key` for a task attaching it.
+pub fn shm_attach(key: usize) -> Option<Vec<Arc<FrameTracker>>> {
+    let mut table = SHM_TABLE.exclusive_access();
+    let segment = table.get_mut(&key)?;
+    segment.attached = true;
+    Some(segment.frames.clone())
+}
+
+/// Free the segments which have been attached but are no longer mapped by any task.
+/// Call it after a task unmaps memory or exits.
+pub fn shm_release_detached() {
+    SHM_TABLE
+        .exclusive_access()
+        .retain(|_, segment| !segment.detached());
+}
@@ -1,14 +1,20 @@
 //! File and filesystem-related syscalls
 
 use crate::mm::translated_byte_buffer;
-use crate::task::current_user_token;
+use crate::task::{current_range_mapped, current_user_token};
 
 const FD_STDOUT: usize = 1;
 
 pub fn sys_write(fd: usize, buf: *const u8, len: usize) -> isize {
     match fd {
         FD_STDOUT => {
-            let buffers = translated_byte_buffer(current_user_token(), buf, len);
+            if !current_range_mapped(buf as usize, len) {
+                return -1;
+            }
+            let buffers = match translated_byte_buffer(current_user_token(), buf, len) {
+                Some(buffers) => buffers,
+                None => return -1,
+            };
             for buffer in buffers {
                 print!("{}", core::str::from_utf8(buffer).unwrap());
             }
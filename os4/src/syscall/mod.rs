@@ -12,32 +12,71 @@
 
 const SYSCALL_WRITE: usize = 64;
 const SYSCALL_EXIT: usize = 93;
+const SYSCALL_SLEEP: usize = 101;
+const SYSCALL_CLOCK_GETTIME: usize = 113;
 const SYSCALL_YIELD: usize = 124;
 const SYSCALL_GET_TIME: usize = 169;
+const SYSCALL_GETPID: usize = 172;
+const SYSCALL_SHMGET: usize = 194;
+const SYSCALL_SHMAT: usize = 196;
+const SYSCALL_SBRK: usize = 214;
 const SYSCALL_MUNMAP: usize = 215;
+const SYSCALL_FORK: usize = 220;
+const SYSCALL_EXEC: usize = 221;
 const SYSCALL_MMAP: usize = 222;
+const SYSCALL_MPROTECT: usize = 226;
+const SYSCALL_WAITPID: usize = 260;
 const SYSCALL_SET_PRIORITY: usize = 140;
+const SYSCALL_SPAWN: usize = 400;
 const SYSCALL_TASK_INFO: usize = 410;
+const SYSCALL_PAGE_STATUS: usize = 411;
+const SYSCALL_CLEAR_REFS: usize = 412;
+const SYSCALL_FRAME_STATS: usize = 414;
+const SYSCALL_NANOSLEEP: usize = 415;
+#[cfg(feature = "test-syscalls")]
+const SYSCALL_GET_PTE_FLAGS: usize = 413;
 
 mod fs;
 mod process;
 
 use fs::*;
 use process::*;
+use crate::mm::FrameStats;
+use crate::task::update_syscall_times;
 
 pub use process::TaskInfo;
 /// handle syscall exception with `syscall_id` and other arguments
 pub fn syscall(syscall_id: usize, args: [usize; 3]) -> isize {
-    // LAB1: You may need to update syscall info here.
+    update_syscall_times(syscall_id);
     match syscall_id {
         SYSCALL_WRITE => sys_write(args[0], args[1] as *const u8, args[2]),
         SYSCALL_EXIT => sys_exit(args[0] as i32),
+        SYSCALL_SLEEP => sys_sleep(args[0]),
+        SYSCALL_NANOSLEEP => sys_nanosleep(args[0] as *const TimeVal),
         SYSCALL_YIELD => sys_yield(),
+        SYSCALL_CLOCK_GETTIME => sys_clock_gettime(args[0], args[1] as *mut TimeSpec),
         SYSCALL_GET_TIME => sys_get_time(args[0] as *mut TimeVal, args[1]),
+        SYSCALL_GETPID => sys_getpid(),
+        SYSCALL_SHMGET => sys_shmget(args[0], args[1]),
+        SYSCALL_SHMAT => sys_shmat(args[0], args[1]),
+        SYSCALL_SBRK => sys_sbrk(args[0] as i32),
+        SYSCALL_FORK => sys_fork(),
+        SYSCALL_EXEC => sys_exec(args[0] as *const u8),
+        SYSCALL_WAITPID => sys_waitpid(args[0] as isize, args[1] as *mut i32),
         SYSCALL_MMAP => sys_mmap(args[0], args[1], args[2]),
         SYSCALL_MUNMAP => sys_munmap(args[0], args[1]),
+        SYSCALL_MPROTECT => sys_mprotect(args[0], args[1], args[2]),
         SYSCALL_SET_PRIORITY => sys_set_priority(args[0] as isize),
+        SYSCALL_SPAWN => sys_spawn(args[0] as *const u8),
         SYSCALL_TASK_INFO => sys_task_info(args[0] as *mut TaskInfo),
-        _ => panic!("Unsupported syscall_id: {}", syscall_id),
+        SYSCALL_CLEAR_REFS => sys_clear_refs(args[0], args[1]),
+        SYSCALL_PAGE_STATUS => sys_page_status(args[0], args[1], args[2] as *mut u8),
+        SYSCALL_FRAME_STATS => sys_frame_stats(args[0] as *mut FrameStats),
+        #[cfg(feature = "test-syscalls")]
+        SYSCALL_GET_PTE_FLAGS => sys_get_pte_flags(args[0]),
+        _ => {
+            error!("[kernel] Unsupported syscall_id: {}", syscall_id);
+            -1
+        }
     }
 }
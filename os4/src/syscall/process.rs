@@ -1,9 +1,13 @@
 //! Process management syscalls
 
-use crate::config::{MAX_SYSCALL_NUM};
-use crate::task::{exit_current_and_run_next, current_task, suspend_current_and_run_next, TaskStatus, current_user_token, mmap, munmap};
-use crate::timer::get_time_us;
-use crate::mm::{copy_kernel_to_user, VirtAddr};
+use crate::config::{MAX_SYSCALL_NUM, USER_SPACE_END};
+use crate::task::{break_current_cow, change_program_brk, clear_refs, exec_current, exit_current_and_run_next, set_priority, sleep_current_and_run_next, fork_current, spawn_current, waitpid, current_range_mapped, current_pid, current_task_info, suspend_current_and_run_next, TaskStatus, current_user_token, mmap, mmap_anywhere, mprotect, munmap, page_status, shmat};
+use crate::timer::{get_monotonic_time_ns, get_time_ns};
+use crate::loader::get_app_data_by_name;
+#[cfg(feature = "test-syscalls")]
+use crate::task::current_pte_flags;
+use core::convert::TryFrom;
+use crate::mm::{copy_kernel_to_user, frame_stats, shm_get, FrameStats, MmapPort, translated_str, UserPtrReader, VirtAddr};
 
 #[repr(C)]
 #[derive(Debug)]
@@ -12,16 +16,44 @@ pub struct TimeVal {
     pub usec: usize,
 }
 
+/// time with nanosecond resolution, for sys_clock_gettime
+#[repr(C)]
+#[derive(Debug)]
+pub struct TimeSpec {
+    pub sec: usize,
+    pub nsec: usize,
+}
+
+/// timezone written back by sys_get_time, as in Linux `gettimeofday`
+#[repr(C)]
+#[derive(Debug)]
+pub struct TimeZone {
+    /// minutes west of Greenwich
+    pub minuteswest: i32,
+    /// type of DST correction
+    pub dsttime: i32,
+}
+
+/// 内核只支持 UTC，写回的时区固定为此值
+const TIMEZONE: TimeZone = TimeZone {
+    minuteswest: 0,
+    dsttime: 0,
+};
+
 #[derive(Clone, Copy)]
 pub struct TaskInfo {
     pub status: TaskStatus,
     pub syscall_times: [u32; MAX_SYSCALL_NUM],
     pub time: usize,
+    /// number of user pages backed by a frame
+    pub resident_pages: usize,
+    /// number of user areas
+    pub area_count: usize,
 }
 
 pub fn sys_exit(exit_code: i32) -> ! {
     info!("[kernel] Application exited with code {}", exit_code);
-    exit_current_and_run_next();
+    exit_current_and_run_next(exit_code);
     panic!("Unreachable in sys_exit!");
 }
 
@@ -31,59 +63,398 @@ pub fn sys_yield() -> isize {
     0
 }
 
+/// 由计时器直接得到的时间
+pub const CLOCK_REALTIME: usize = 0;
+/// 单调时间，即使计时器被重置也不会倒退
+pub const CLOCK_MONOTONIC: usize = 1;
+
+/// 当前任务睡眠 ms 毫秒，期间不会被调度，返回 0
+pub fn sys_sleep(ms: usize) -> isize {
+    sleep_current_and_run_next(ms.saturating_mul(1000));
+    0
+}
+
+/*
+    当前任务睡眠 req 指定的时长，期间不会被调度
+    参数：
+    req 用户空间的 TimeVal，可以跨越页边界，usec 须小于 1000000
+    返回值：睡眠结束后返回 0，req 不可读或 usec 不合法时立即返回 -1
+*/
+pub fn sys_nanosleep(req: *const TimeVal) -> isize {
+    let mut duration = TimeVal { sec: 0, usec: 0 };
+    if UserPtrReader::new(current_user_token())
+        .read_struct(req as usize, &mut duration)
+        .is_err()
+        || duration.usec >= 1_000_000
+    {
+        return -1;
+    }
+    let us = duration
+        .sec
+        .saturating_mul(1_000_000)
+        .saturating_add(duration.usec);
+    sleep_current_and_run_next(us);
+    0
+}
+
 // YOUR JOB: 引入虚地址后重写 sys_get_time
+/// 读取的是 CLOCK_REALTIME，与 sys_clock_gettime 一致，只是精度为微秒
+/// tz 不为 0 时写回固定的时区 TIMEZONE；tz 不可写时返回 -1，此时 ts 不会被写入
 pub fn sys_get_time(_ts: *mut TimeVal, _tz: usize) -> isize {
-    let us = get_time_us();
-    let tmp = TimeVal {
-        sec: us / 1_000_000,
-        usec: us % 1_000_000,
+    let user = UserPtrReader::new(current_user_token());
+    if _tz != 0 && write_to_user(&user, _tz as *mut TimeZone, TIMEZONE) != 0 {
+        return -1;
+    }
+    let ns = clock_time_ns(CLOCK_REALTIME).unwrap();
+    write_to_user(
+        &user,
+        _ts,
+        TimeVal {
+            sec: ns / 1_000_000_000,
+            usec: ns % 1_000_000_000 / 1000,
+        },
+    )
+}
+
+/// 指定时钟的当前时间，单位为纳秒，时钟不存在时返回 None
+fn clock_time_ns(clock_id: usize) -> Option<usize> {
+    match clock_id {
+        CLOCK_REALTIME => Some(get_time_ns()),
+        CLOCK_MONOTONIC => Some(get_monotonic_time_ns()),
+        _ => None,
+    }
+}
+
+/*
+    读取指定时钟的时间，精度为纳秒
+    参数：
+    clock_id CLOCK_REALTIME 或 CLOCK_MONOTONIC，两者都从启动时开始计时
+    ts 用户空间的 TimeSpec
+    返回值：执行成功则返回 0，时钟不存在或 ts 不可写返回 -1
+*/
+pub fn sys_clock_gettime(clock_id: usize, ts: *mut TimeSpec) -> isize {
+    let ns = match clock_time_ns(clock_id) {
+        Some(ns) => ns,
+        None => return -1,
     };
-    copy_kernel_to_user(current_user_token(), &tmp as *const TimeVal as *const u8, _ts as usize, core::mem::size_of::<TimeVal>());
-    0
+    write_to_user(
+        &UserPtrReader::new(current_user_token()),
+        ts,
+        TimeSpec {
+            sec: ns / 1_000_000_000,
+            nsec: ns % 1_000_000_000,
+        },
+    )
+}
+
+/// 把 value 写入当前任务用户空间的 ptr 处，可以跨越页边界，ptr 不可写时返回 -1
+fn write_to_user<T>(user: &UserPtrReader, ptr: *mut T, value: T) -> isize {
+    let size = core::mem::size_of::<T>();
+    if !current_range_mapped(ptr as usize, size) {
+        return -1;
+    }
+    break_current_cow(ptr as usize, size);
+    match user.write_struct(ptr as usize, &value) {
+        Ok(()) => 0,
+        Err(()) => -1,
+    }
+}
+
+/// 复制当前任务，父子任务的用户页以写时复制的方式共享
+/// 父任务返回子任务的 pid，子任务返回 0，页帧不足时返回 -1
+pub fn sys_fork() -> isize {
+    match fork_current() {
+        Some(pid) => pid as isize,
+        None => -1,
+    }
+}
+
+/// 用名为 path 的程序替换当前任务的地址空间，从其入口重新开始执行
+/// 程序不存在、path 无法读取或页帧不足时返回 -1，此时当前任务不受影响
+pub fn sys_exec(path: *const u8) -> isize {
+    let path = match translated_str(current_user_token(), path) {
+        Ok(path) => path,
+        Err(()) => return -1,
+    };
+    match get_app_data_by_name(path.as_str()) {
+        Some(data) if exec_current(data) => 0,
+        _ => -1,
+    }
+}
+
+/// 以名为 path 的程序创建一个新的子任务，不复制也不改动当前任务的地址空间
+/// 返回子任务的 pid，程序不存在、path 无法读取或页帧不足时返回 -1
+pub fn sys_spawn(path: *const u8) -> isize {
+    let path = match translated_str(current_user_token(), path) {
+        Ok(path) => path,
+        Err(()) => return -1,
+    };
+    match get_app_data_by_name(path.as_str()) {
+        Some(data) => match spawn_current(data) {
+            Some(pid) => pid as isize,
+            None => -1,
+        },
+        None => -1,
+    }
+}
+
+/*
+    回收一个已退出的子任务
+    参数：
+    pid 要等待的子任务的 pid，为 -1 时等待任意子任务
+    exit_code_ptr 用户空间的 i32，用于写回子任务的退出码，为空指针时不写回
+    返回值：执行成功则返回子任务的 pid；子任务存在但尚未退出返回 -2；
+          不存在这样的子任务或 exit_code_ptr 非空且不可写返回 -1
+*/
+pub fn sys_waitpid(pid: isize, exit_code_ptr: *mut i32) -> isize {
+    let user = UserPtrReader::new(current_user_token());
+    // check that the exit code can be written back before the child is reaped
+    if !exit_code_ptr.is_null() && write_to_user(&user, exit_code_ptr, 0) != 0 {
+        return -1;
+    }
+    match waitpid(pid) {
+        Ok((child_pid, exit_code)) => {
+            if !exit_code_ptr.is_null() {
+                user.write_struct(exit_code_ptr as usize, &exit_code).unwrap();
+            }
+            child_pid as isize
+        }
+        Err(err) => err,
+    }
+}
+
+/// pid of the current task, app pids start from 1
+pub fn sys_getpid() -> isize {
+    current_pid() as isize
+}
+
+/// 调整堆顶 program_brk，增长时映射新页（R|W|U），收缩时释放多出的页
+/// 成功返回原来的堆顶；增长与已有映射重叠或收缩到堆底以下时返回 -1
+pub fn sys_sbrk(size: i32) -> isize {
+    match change_program_brk(size as isize) {
+        Some(old_brk) => old_brk as isize,
+        None => -1,
+    }
+}
+
+/// 设置当前任务的优先级，用于 stride 调度，优先级越高分到的时间片越多
+/// 成功返回 prio；prio 小于 2 时返回 -1
+pub fn sys_set_priority(prio: isize) -> isize {
+    if prio < 2 {
+        return -1;
+    }
+    set_priority(prio as usize);
+    prio
+}
+
+/// End of `[start, start + len)`, or `None` if the range wraps around or
+/// reaches beyond the user part of the address space. This keeps the
+/// trampoline and the trap context, which live at the very top, out of reach.
+fn user_end_va(start: usize, len: usize) -> Option<VirtAddr> {
+    start
+        .checked_add(len)
+        .filter(|end| *end <= USER_SPACE_END)
+        .map(VirtAddr::from)
 }
 
-// CLUE: 从 ch4 开始不再对调度算法进行测试~
-pub fn sys_set_priority(_prio: isize) -> isize {
-    -1
+/// 检查 port 的权限位，保留位被置位或没有任何权限时返回 None
+fn mmap_port(port: usize) -> Option<MmapPort> {
+    u8::try_from(port).ok().and_then(MmapPort::from_bits)
 }
 
 // YOUR JOB: 扩展内核以实现 sys_mmap 和 sys_munmap
+/// sys_mmap 的 port 扩展位：只登记区间，首次访问时再分配物理页
+pub const MMAP_LAZY: usize = 1 << 8;
+
 /* 
     申请内存
     参数：
-    start 需要映射的虚存起始地址，要求按页对齐
+    start 需要映射的虚存起始地址，要求按页对齐，且第 39 位及以上与第 38 位相同（Sv39 的规范地址）；
+          为 0 时由内核选择一段空闲的地址，
+          第 0 页不会被映射，空指针不能指向由用户程序选择的内存
     len 申请的字节长度
-    port：第 0 位表示是否可读，第 1 位表示是否可写，第 2 位表示是否可执行。其他位无效且必须为 0
-    返回值：执行成功则返回 0，错误返回 -1
+    port：第 0 位表示是否可读，第 1 位表示是否可写，第 2 位表示是否可执行。
+          扩展位 MMAP_LAZY 表示按需分配物理页。其他位无效且必须为 0。
+          可执行的页总是同时可读，保证页表项是合法的叶子编码
+    返回值：执行成功则返回 0（start 为 0 时返回内核选择的起始地址），
+          错误（包括 start + len 溢出或超出用户地址空间）返回 -1
 */
 pub fn sys_mmap(_start: usize, _len: usize, _port: usize) -> isize {
     let start_va = VirtAddr::from(_start);
-    if ! start_va.aligned() || _port & !0x7 != 0 || _port & 0x7 == 0 {
+    let lazy = _port & MMAP_LAZY != 0;
+    let port = match mmap_port(_port & !MMAP_LAZY) {
+        Some(port) => port,
+        None => return -1,
+    };
+    if !start_va.is_canonical() || !start_va.aligned() {
         return -1;
     }
+    if _start == 0 {
+        if _len == 0 || _len > USER_SPACE_END {
+            return -1;
+        }
+        return mmap_anywhere(_len, port, lazy);
+    }
+    let end_va = match user_end_va(_start, _len) {
+        Some(end_va) => end_va,
+        None => return -1,
+    };
     if _len == 0 {
         return 0;
     }
-
-    let end_va = VirtAddr::from(_start+_len);
-    mmap(start_va, end_va, _port)
+    mmap(start_va, end_va, port, lazy)
 }
 
 pub fn sys_munmap(_start: usize, _len: usize) -> isize {
     let start_va = VirtAddr::from(_start);
-    if ! start_va.aligned() {
+    if !start_va.is_canonical() || !start_va.aligned() {
         return -1;
     }
+    let end_va = match user_end_va(_start, _len) {
+        Some(end_va) => end_va,
+        None => return -1,
+    };
     if _len == 0 {
         return 0;
     }
-    let end_va = VirtAddr::from(usize::from(start_va)+_len);
     munmap(start_va, end_va)
 }
 
+/*
+    创建共享内存段
+    参数：
+    key 共享内存段的标识，段已存在时只检查其大小
+    len 字节长度，必须大于 0
+    返回值：执行成功则返回 0，已存在的段小于 len 或物理页不足返回 -1
+*/
+pub fn sys_shmget(key: usize, len: usize) -> isize {
+    if len == 0 || !shm_get(key, len) {
+        return -1;
+    }
+    0
+}
+
+/*
+    把共享内存段映射到当前任务，权限为 U|R|W
+    参数：
+    key 共享内存段的标识
+    addr 映射的虚存起始地址，要求按页对齐，不能为第 0 页
+    返回值：执行成功则返回 0，段不存在、地址未对齐、位于第 0 页或与已有映射重叠返回 -1
+    最后一个映射该段的任务 munmap 或退出后，段的物理页被释放
+*/
+pub fn sys_shmat(key: usize, addr: usize) -> isize {
+    let start_va = VirtAddr::from(addr);
+    if !start_va.aligned() {
+        return -1;
+    }
+    shmat(key, start_va)
+}
+
+/*
+    修改已映射内存的访问权限
+    参数：
+    start 需要修改的虚存起始地址，要求按页对齐
+    len 字节长度
+    port：与 sys_mmap 相同，第 0 位表示是否可读，第 1 位表示是否可写，第 2 位表示是否可执行
+    返回值：执行成功则返回 0，区间内存在未映射的页或参数错误返回 -1
+*/
+pub fn sys_mprotect(_start: usize, _len: usize, _port: usize) -> isize {
+    let start_va = VirtAddr::from(_start);
+    let port = match mmap_port(_port) {
+        Some(port) => port,
+        None => return -1,
+    };
+    if ! start_va.aligned() {
+        return -1;
+    }
+    let end_va = match user_end_va(_start, _len) {
+        Some(end_va) => end_va,
+        None => return -1,
+    };
+    if _len == 0 {
+        return 0;
+    }
+    mprotect(start_va, end_va, port)
+}
+
 // YOUR JOB: 引入虚地址后重写 sys_task_info
 pub fn sys_task_info(ti: *mut TaskInfo) -> isize {
-    let task = current_task();
-    copy_kernel_to_user(current_user_token(), &task as *const TaskInfo as *const u8, ti as usize, core::mem::size_of::<TaskInfo>());
+    let task_info: TaskInfo = current_task_info();
+    write_to_user(&UserPtrReader::new(current_user_token()), ti, task_info)
+}
+
+/*
+    查询页的访问状态
+    参数：
+    start 起始虚存地址，要求按页对齐
+    len 字节长度
+    buf 用户缓冲区，区间内每页写入一个字节：第 0 位为 accessed，第 1 位为 dirty
+    返回值：执行成功则返回 0，区间内存在未映射的页、参数错误或 buf 不可写返回 -1
+*/
+pub fn sys_page_status(start: usize, len: usize, buf: *mut u8) -> isize {
+    let start_va = VirtAddr::from(start);
+    if !start_va.aligned() {
+        return -1;
+    }
+    let end_va = match user_end_va(start, len) {
+        Some(end_va) => end_va,
+        None => return -1,
+    };
+    let status = match page_status(start_va, end_va) {
+        Some(status) => status,
+        None => return -1,
+    };
+    if !current_range_mapped(buf as usize, status.len()) {
+        return -1;
+    }
+    break_current_cow(buf as usize, status.len());
+    if copy_kernel_to_user(current_user_token(), status.as_ptr(), buf as usize, status.len()).is_err() {
+        return -1;
+    }
     0
 }
+
+/*
+    清除页的 accessed 与 dirty 位，并刷新 TLB 使硬件在下次访问时重新置位
+    参数：
+    start 起始虚存地址，要求按页对齐
+    len 字节长度
+    返回值：执行成功则返回 0，区间内存在未映射的页或参数错误返回 -1
+*/
+pub fn sys_clear_refs(start: usize, len: usize) -> isize {
+    let start_va = VirtAddr::from(start);
+    if !start_va.aligned() {
+        return -1;
+    }
+    match user_end_va(start, len) {
+        Some(end_va) => clear_refs(start_va, end_va),
+        None => -1,
+    }
+}
+
+/*
+    读取物理页帧的使用情况，用于从用户态排查页帧泄漏
+    参数：
+    out 用户空间的 FrameStats，写回总页帧数 total、已分配的 used 与空闲的 free
+    返回值：执行成功则返回 0，out 不可写返回 -1
+*/
+pub fn sys_frame_stats(out: *mut FrameStats) -> isize {
+    write_to_user(&UserPtrReader::new(current_user_token()), out, frame_stats())
+}
+
+/*
+    读取页表项的标志位，仅供测试使用，需以 test-syscalls 特性编译内核
+    参数：
+    va 虚存地址，无需对齐
+    返回值：va 所在页已映射时返回其页表项的低 10 位标志位（V R W X U G A D 与保留位），未映射返回 -1
+*/
+#[cfg(feature = "test-syscalls")]
+pub fn sys_get_pte_flags(va: usize) -> isize {
+    if va >= USER_SPACE_END {
+        return -1;
+    }
+    match current_pte_flags(VirtAddr::from(va)) {
+        Some(flags) => flags as isize,
+        None => -1,
+    }
+}
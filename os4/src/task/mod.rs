@@ -10,21 +10,33 @@
 //! might not be what you expect.
 
 mod context;
+mod pid;
+mod ranges;
 mod switch;
 #[allow(clippy::module_inception)]
 mod task;
-use crate::mm::{VirtAddr, MapPermission};
+use crate::mm::{free_frame_count, shm_attach, shm_release_detached, VirtAddr, VirtPageNum, MapPermission, MmapPort};
 use crate::loader::{get_app_data, get_num_app};
 use crate::sync::UPSafeCell;
 use crate::trap::TrapContext;
 use alloc::vec::Vec;
 use lazy_static::*;
+use riscv::asm::wfi;
 pub use switch::__switch;
 pub use task::{TaskControlBlock, TaskStatus};
-use crate::config::{MAX_SYSCALL_NUM};
+#[cfg(feature = "self-tests")]
+pub use task::stride_test;
+use task::stride_less;
+use crate::config::{MAX_SYSCALL_NUM, USER_SPACE_END};
 use crate::syscall::TaskInfo;
-use crate::timer::get_time_us;
+use crate::timer::{get_time_us, set_next_trigger};
 pub use context::TaskContext;
+use pid::{pid_alloc, pid_dealloc, IDLE_PID, INIT_PID};
+#[cfg(feature = "self-tests")]
+pub use pid::pid_allocator_test;
+use ranges::MappedRanges;
+#[cfg(feature = "self-tests")]
+pub use ranges::mapped_ranges_test;
 
 /// The task manager, where all the tasks are managed.
 ///
@@ -36,8 +48,6 @@ pub use context::TaskContext;
 /// borrowing checks to runtime. You can see examples on how to use `inner` in
 /// existing functions on `TaskManager`.
 pub struct TaskManager {
-    /// total number of tasks
-    num_app: usize,
     /// use inner value to get mutable access
     inner: UPSafeCell<TaskManagerInner>,
 }
@@ -50,6 +60,35 @@ struct TaskManagerInner {
     current_task: usize,
 }
 
+impl TaskManagerInner {
+    /// Slot for a new task in the task list, which also picks its kernel stack:
+    /// that of an `Exited` task, whose kernel stack is released so that the new
+    /// task maps its own there, or a new one at the end. The init task keeps
+    /// slot 0 even after it exits.
+    fn free_slot(&mut self) -> usize {
+        match self
+            .tasks
+            .iter()
+            .skip(1)
+            .position(|task| task.task_status == TaskStatus::Exited)
+        {
+            Some(idx) => {
+                self.tasks[idx + 1].kernel_stack = None;
+                idx + 1
+            }
+            None => self.tasks.len(),
+        }
+    }
+    /// Put `task` into `slot` from `free_slot`, dropping the exited task there.
+    fn put_task(&mut self, slot: usize, task: TaskControlBlock) {
+        if slot == self.tasks.len() {
+            self.tasks.push(task);
+        } else {
+            self.tasks[slot] = task;
+        }
+    }
+}
+
 lazy_static! {
     /// a `TaskManager` instance through lazy_static!
     pub static ref TASK_MANAGER: TaskManager = {
@@ -58,10 +97,9 @@ lazy_static! {
         info!("num_app = {}", num_app);
         let mut tasks: Vec<TaskControlBlock> = Vec::new();
         for i in 0..num_app {
-            tasks.push(TaskControlBlock::new(get_app_data(i), i));
+            tasks.push(TaskControlBlock::new(get_app_data(i), i).unwrap());
         }
         TaskManager {
-            num_app,
             inner: unsafe {
                 UPSafeCell::new(TaskManagerInner {
                     tasks,
@@ -81,7 +119,8 @@ impl TaskManager {
         let mut inner = self.inner.exclusive_access();
         let next_task = &mut inner.tasks[0];
         next_task.task_status = TaskStatus::Running;
-        next_task.start_time = get_time_us();
+        next_task.record_first_dispatch();
+        next_task.advance_stride();
         let next_task_cx_ptr = &next_task.task_cx as *const TaskContext;
         drop(inner);
         let mut _unused = TaskContext::zero_init();
@@ -99,22 +138,173 @@ impl TaskManager {
         inner.tasks[current].task_status = TaskStatus::Ready;
     }
 
-    /// Change the status of current `Running` task into `Exited`.
-    fn mark_current_exited(&self) {
+    /// Change the status of current `Running` task into `Sleeping` for `us` microseconds.
+    fn mark_current_sleeping(&self, us: usize) {
+        let mut inner = self.inner.exclusive_access();
+        let current = inner.current_task;
+        inner.tasks[current].task_status = TaskStatus::Sleeping;
+        inner.tasks[current].wake_time = get_time_us().saturating_add(us);
+    }
+
+    /// Release the memory of the current `Running` task and keep `exit_code` for
+    /// its parent. A task with a parent turns `Zombie` until the parent waits for
+    /// it, the others become `Exited` and release their pid at once.
+    fn mark_current_exited(&self, exit_code: i32) {
         let mut inner = self.inner.exclusive_access();
         let current = inner.current_task;
-        inner.tasks[current].task_status = TaskStatus::Exited;
+        let pid = inner.tasks[current].pid;
+        let task = &mut inner.tasks[current];
+        task.exit_code = exit_code;
+        task.memory_set.recycle_data_pages();
+        task.mapped_ranges.clear();
+        if task.parent.is_some() {
+            task.task_status = TaskStatus::Zombie;
+        } else {
+            // still running on its kernel stack, which is released with the slot
+            task.task_status = TaskStatus::Exited;
+            pid_dealloc(pid);
+        }
+        // the children are adopted by the init task, which is the first in task list;
+        // once it is gone too nobody can wait for them, so their zombies are reaped here
+        let init_alive = pid != INIT_PID
+            && !matches!(
+                inner.tasks[0].task_status,
+                TaskStatus::Exited | TaskStatus::Zombie
+            );
+        for child in inner.tasks.iter_mut().filter(|task| {
+            task.parent == Some(pid) && task.task_status != TaskStatus::Exited
+        }) {
+            if init_alive {
+                child.parent = Some(INIT_PID);
+            } else {
+                child.parent = None;
+                if child.task_status == TaskStatus::Zombie {
+                    child.task_status = TaskStatus::Exited;
+                    child.kernel_stack = None;
+                    pid_dealloc(child.pid);
+                }
+            }
+        }
+        drop(inner);
+        shm_release_detached();
+    }
+
+    /// Reap a `Zombie` child of the current task, `pid == -1` meaning any child.
+    /// Returns its pid and exit code, `Err(-2)` if the child is still running or
+    /// `Err(-1)` if there is no such child.
+    fn waitpid(&self, pid: isize) -> Result<(usize, i32), isize> {
+        let mut inner = self.inner.exclusive_access();
+        let current_pid = inner.tasks[inner.current_task].pid;
+        let mut found = false;
+        // an `Exited` task's pid may have been reused, skip them
+        for child in inner.tasks.iter_mut().filter(|task| {
+            task.parent == Some(current_pid)
+                && task.task_status != TaskStatus::Exited
+                && (pid == -1 || task.pid as isize == pid)
+        }) {
+            found = true;
+            if child.task_status == TaskStatus::Zombie {
+                child.task_status = TaskStatus::Exited;
+                child.kernel_stack = None;
+                pid_dealloc(child.pid);
+                return Ok((child.pid, child.exit_code));
+            }
+        }
+        if found {
+            Err(-2)
+        } else {
+            Err(-1)
+        }
     }
 
     /// Find next task to run and return task id.
     ///
-    /// In this case, we only return the first `Ready` task in task list.
+    /// In this case, we return the `Ready` task with the smallest stride, taking
+    /// them in turn from the one after the current task on ties. `Sleeping` tasks
+    /// whose wake time has passed become `Ready` first; `Zombie` tasks are never
+    /// scheduled again.
     fn find_next_task(&self) -> Option<usize> {
-        let inner = self.inner.exclusive_access();
+        let mut inner = self.inner.exclusive_access();
+        let now = get_time_us();
+        for task in inner.tasks.iter_mut().filter(|task| {
+            task.task_status == TaskStatus::Sleeping && task.wake_time <= now
+        }) {
+            task.task_status = TaskStatus::Ready;
+        }
         let current = inner.current_task;
-        (current + 1..current + self.num_app + 1)
-            .map(|id| id % self.num_app)
-            .find(|id| inner.tasks[*id].task_status == TaskStatus::Ready)
+        let num_task = inner.tasks.len();
+        (current + 1..current + num_task + 1)
+            .map(|id| id % num_task)
+            .filter(|id| inner.tasks[*id].task_status == TaskStatus::Ready)
+            .reduce(|best, id| {
+                if stride_less(inner.tasks[id].stride, inner.tasks[best].stride) {
+                    id
+                } else {
+                    best
+                }
+            })
+    }
+
+    /// Resolve the copy-on-write pages of the current task in `[start, start + len)`.
+    fn break_current_cow(&self, start: usize, len: usize) {
+        let end = match start.checked_add(len) {
+            Some(end) if end <= USER_SPACE_END => end,
+            // not a user range, the copy fails anyway
+            _ => return,
+        };
+        let mut inner = self.inner.exclusive_access();
+        let cur_task_id = inner.current_task;
+        inner.tasks[cur_task_id]
+            .memory_set
+            .break_cow_range(start.into(), end.into());
+    }
+
+    /// Replace the current task's program with the one in `elf_data`.
+    /// Returns false, leaving the task as it was, if frames run out.
+    fn exec_current(&self, elf_data: &[u8]) -> bool {
+        let mut inner = self.inner.exclusive_access();
+        let cur_task_id = inner.current_task;
+        if !inner.tasks[cur_task_id].exec(elf_data) {
+            return false;
+        }
+        drop(inner);
+        shm_release_detached();
+        true
+    }
+
+    /// Fork the current task and add the child to the task list, returning its pid,
+    /// or `None` if frames run out.
+    fn fork_current(&self) -> Option<usize> {
+        let mut inner = self.inner.exclusive_access();
+        let current = inner.current_task;
+        // kernel stacks are indexed by position in the task list
+        let slot = inner.free_slot();
+        let child = inner.tasks[current].fork(slot)?;
+        let pid = child.pid;
+        inner.put_task(slot, child);
+        Some(pid)
+    }
+
+    /// Create a child of the current task running the program in `elf_data`,
+    /// leaving the current address space untouched. Returns the child's pid,
+    /// or `None` if frames run out.
+    fn spawn_current(&self, elf_data: &[u8]) -> Option<usize> {
+        let mut inner = self.inner.exclusive_access();
+        let current = inner.current_task;
+        let slot = inner.free_slot();
+        let mut child = TaskControlBlock::new(elf_data, slot)?;
+        child.parent = Some(inner.tasks[current].pid);
+        // start level with the parent, as strides only compare well when close
+        child.stride = inner.tasks[current].stride;
+        let pid = child.pid;
+        inner.put_task(slot, child);
+        Some(pid)
+    }
+
+    /// Get the current 'Running' task's pid.
+    fn get_current_pid(&self) -> usize {
+        let inner = self.inner.exclusive_access();
+        inner.tasks[inner.current_task].pid
     }
 
     /// Get the current 'Running' task's token.
@@ -130,16 +320,34 @@ impl TaskManager {
         inner.tasks[inner.current_task].get_trap_cx()
     }
 
+    /// Whether any task is waiting to wake up.
+    fn has_sleeping_task(&self) -> bool {
+        let inner = self.inner.exclusive_access();
+        inner
+            .tasks
+            .iter()
+            .any(|task| task.task_status == TaskStatus::Sleeping)
+    }
+
     /// Switch current `Running` task to the task we have found,
-    /// or there is no `Ready` task and we can exit with all applications completed
+    /// or there is no `Ready` task and we can exit with all applications completed.
+    /// While the only tasks left are sleeping, idle until a timer tick and check
+    /// again, so a sleeping system does not keep the hart busy.
     fn run_next_task(&self) {
-        if let Some(next) = self.find_next_task() {
+        let mut next_task = self.find_next_task();
+        while next_task.is_none() && self.has_sleeping_task() {
+            // supervisor interrupts are off in the kernel, but a pending timer
+            // interrupt still ends wfi; rearming the timer clears the last one
+            set_next_trigger();
+            unsafe { wfi() };
+            next_task = self.find_next_task();
+        }
+        if let Some(next) = next_task {
             let mut inner = self.inner.exclusive_access();
             let current = inner.current_task;
             inner.tasks[next].task_status = TaskStatus::Running;
-            if inner.tasks[next].start_time == 0 {
-                inner.tasks[next].start_time = get_time_us();
-            }
+            inner.tasks[next].record_first_dispatch();
+            inner.tasks[next].advance_stride();
             inner.current_task = next;
             let current_task_cx_ptr = &mut inner.tasks[current].task_cx as *mut TaskContext;
             let next_task_cx_ptr = &inner.tasks[next].task_cx as *const TaskContext;
@@ -154,54 +362,176 @@ impl TaskManager {
         }
     }
     
-    fn get_current_task(&self) -> TaskInfo {
+    /// Get a snapshot of the current 'Running' task's info.
+    fn get_current_task_info(&self) -> TaskInfo {
         let inner = self.inner.exclusive_access();
         let current_task = &inner.tasks[inner.current_task];
         let mut ti = TaskInfo {
             status: TaskStatus::UnInit,
             syscall_times: [0; MAX_SYSCALL_NUM],
-            time: 0
+            time: 0,
+            resident_pages: 0,
+            area_count: 0,
         };
         ti.status = current_task.task_status;
         ti.syscall_times.copy_from_slice(&current_task.syscall_times[..]);
-        ti.time = (get_time_us() - current_task.start_time) / 1000;
+        ti.time = current_task.running_time_ms();
+        ti.resident_pages = current_task.memory_set.resident_pages();
+        ti.area_count = current_task.memory_set.user_area_count();
         ti
     }
 
+    /// Count one more call of `syscall_id` for the current task, ignoring unknown ids.
     pub fn update_syscall_times(&self, syscall_id: usize) {
+        if syscall_id >= MAX_SYSCALL_NUM {
+            return;
+        }
         let mut inner = self.inner.exclusive_access();
         let current = inner.current_task;
         inner.tasks[current].syscall_times[syscall_id] += 1;
     }
 
-    pub fn mmap(&self, start_va: VirtAddr, end_va: VirtAddr, port: usize) -> isize {
+    /// Map `[start_va, end_va)` in the current task, returning 0 or -1.
+    ///
+    /// The zero page is never mapped on request, so that a null pointer can't be
+    /// made to point at memory chosen by the task.
+    pub fn mmap(&self, start_va: VirtAddr, end_va: VirtAddr, port: MmapPort, lazy: bool) -> isize {
         let mut inner = TASK_MANAGER.inner.exclusive_access();
         let cur_task_id = inner.current_task;
         let cur_task = &mut inner.tasks[cur_task_id];
         let mem_set = &mut cur_task.memory_set;
         let end_va = end_va.ceil().into();
-        if mem_set.conflict_with_range(start_va, end_va) {
+        // reject requests that cannot fit before anything else; this is not
+        // enough on its own since page tables take frames too, the rollback stays
+        if !lazy && end_va.floor().0 - start_va.floor().0 > free_frame_count() {
             return -1;
         }
-        let mut perm = MapPermission::U;
-        if (port & (1 << 0)) != 0 {
-            perm |= MapPermission::R;
-        }
-        if (port & (1 << 1)) != 0 {
-            perm |= MapPermission::W;
+        if start_va.floor().0 == 0 || mem_set.conflict_with_range(start_va, end_va) {
+            return -1;
         }
-        if (port & (1 << 2)) != 0 {
-            perm |= MapPermission::X;
+        let perm = port_to_permission(port);
+        if lazy {
+            mem_set.insert_lazy_area(start_va, end_va, perm);
+        } else if !mem_set.try_insert_framed_area(start_va, end_va, perm) {
+            return -1;
         }
-        mem_set.insert_framed_area(
-            start_va,
-            end_va,
-            perm
-        );
+        cur_task.mapped_ranges.insert(start_va.floor(), end_va.ceil());
         info!("mmap: [{:#x}, {:#x}]", usize::from(start_va), usize::from(end_va));
         0
     }
 
+    /// Map `len` bytes at an address chosen by the kernel, returning it or -1.
+    pub fn mmap_anywhere(&self, len: usize, port: MmapPort, lazy: bool) -> isize {
+        let inner = TASK_MANAGER.inner.exclusive_access();
+        let start_va = match inner.tasks[inner.current_task].memory_set.find_free_area(len) {
+            Some(start_va) => start_va,
+            None => return -1,
+        };
+        drop(inner);
+        let end_va = VirtAddr::from(usize::from(start_va) + len);
+        match self.mmap(start_va, end_va, port, lazy) {
+            0 => usize::from(start_va) as isize,
+            _ => -1,
+        }
+    }
+
+    pub fn mprotect(&self, start_va: VirtAddr, end_va: VirtAddr, port: MmapPort) -> isize {
+        let mut inner = TASK_MANAGER.inner.exclusive_access();
+        let cur_task_id = inner.current_task;
+        let cur_task = &mut inner.tasks[cur_task_id];
+        let mem_set = &mut cur_task.memory_set;
+        let start_vn = start_va.floor();
+        let end_vn = end_va.ceil();
+        let ret = mem_set.protect_range(start_vn, end_vn, port_to_permission(port));
+        info!("mprotect: [{:#x}, {:#x}] port = {:#x}", usize::from(start_vn), usize::from(end_vn), port.bits());
+        ret
+    }
+
+    /// Try to resolve a page fault of the current task at `va`, see
+    /// [`MemorySet::handle_page_fault`](crate::mm::MemorySet::handle_page_fault).
+    pub fn handle_page_fault(&self, va: VirtAddr) -> bool {
+        let mut inner = TASK_MANAGER.inner.exclusive_access();
+        let cur_task_id = inner.current_task;
+        inner.tasks[cur_task_id].memory_set.handle_page_fault(va)
+    }
+
+    /// Map the shared memory segment `key` into the current task from `start_va` on.
+    pub fn shmat(&self, key: usize, start_va: VirtAddr) -> isize {
+        // the zero page stays unmapped as in `mmap`
+        if start_va.floor().0 == 0 {
+            return -1;
+        }
+        let frames = match shm_attach(key) {
+            Some(frames) => frames,
+            None => return -1,
+        };
+        let mut inner = self.inner.exclusive_access();
+        let cur_task_id = inner.current_task;
+        let pages = frames.len();
+        let cur_task = &mut inner.tasks[cur_task_id];
+        if !cur_task.memory_set.attach_shared(start_va, frames) {
+            return -1;
+        }
+        let start_vn = start_va.floor();
+        cur_task
+            .mapped_ranges
+            .insert(start_vn, VirtPageNum(start_vn.0 + pages));
+        info!("shmat: key = {}, start = {:#x}", key, usize::from(start_va));
+        0
+    }
+
+    /// Set the scheduling priority of the current task.
+    pub fn set_current_priority(&self, priority: usize) {
+        let mut inner = self.inner.exclusive_access();
+        let cur_task_id = inner.current_task;
+        inner.tasks[cur_task_id].set_priority(priority);
+    }
+
+    /// Move the current task's program break by `size` bytes, returning the old break.
+    pub fn change_current_program_brk(&self, size: isize) -> Option<usize> {
+        let mut inner = self.inner.exclusive_access();
+        let cur_task_id = inner.current_task;
+        inner.tasks[cur_task_id].change_program_brk(size)
+    }
+
+    /// Accessed/dirty status of the current task's pages in `[start_va, end_va)`,
+    /// see [`MemorySet::page_status`](crate::mm::MemorySet::page_status).
+    pub fn page_status(&self, start_va: VirtAddr, end_va: VirtAddr) -> Option<Vec<u8>> {
+        let inner = self.inner.exclusive_access();
+        inner.tasks[inner.current_task]
+            .memory_set
+            .page_status(start_va.floor(), end_va.ceil())
+    }
+
+    /// Clear the accessed/dirty bits of the current task's pages in `[start_va, end_va)`.
+    pub fn clear_refs(&self, start_va: VirtAddr, end_va: VirtAddr) -> isize {
+        let mut inner = self.inner.exclusive_access();
+        let cur_task_id = inner.current_task;
+        inner.tasks[cur_task_id]
+            .memory_set
+            .clear_refs(start_va.floor(), end_va.ceil())
+    }
+
+    /// Flag bits of the page table entry of `va` in the current task.
+    #[cfg(feature = "test-syscalls")]
+    pub fn pte_flags(&self, va: VirtAddr) -> Option<usize> {
+        let inner = self.inner.exclusive_access();
+        inner.tasks[inner.current_task].memory_set.pte_flags(va.floor())
+    }
+
+    /// Whether `[start, start + len)` is mapped in the current task, see
+    /// [`TaskControlBlock::is_range_mapped`].
+    pub fn is_current_range_mapped(&self, start: usize, len: usize) -> bool {
+        let inner = self.inner.exclusive_access();
+        inner.tasks[inner.current_task].is_range_mapped(start, len)
+    }
+
+    /// Whether `va` lies in the stack guard page of the current task.
+    pub fn in_stack_guard(&self, va: VirtAddr) -> bool {
+        let inner = TASK_MANAGER.inner.exclusive_access();
+        inner.tasks[inner.current_task].memory_set.in_stack_guard(va)
+    }
+
     pub fn munmap(&self, start_va: VirtAddr, end_va: VirtAddr) -> isize {
         let mut inner = TASK_MANAGER.inner.exclusive_access();
         let cur_task_id = inner.current_task;
@@ -209,13 +539,23 @@ impl TaskManager {
         let mem_set = &mut cur_task.memory_set;
         let start_vn = start_va.floor();
         let end_vn = end_va.ceil();
-        let ret = mem_set.unmap_area_by_exact_range(start_vn, end_vn);
+        let ret = mem_set.unmap_range(start_vn, end_vn);
+        if ret == 0 {
+            cur_task.mapped_ranges.remove(start_vn, end_vn);
+        }
+        drop(inner);
+        shm_release_detached();
         info!("munmap: [{:#x}, {:#x}]", usize::from(start_vn), usize::from(end_vn));
         ret
     }
 
 }
 
+/// Convert the `port` of mmap/mprotect to the permission of a user area.
+fn port_to_permission(port: MmapPort) -> MapPermission {
+    MapPermission::from_bits_truncate(port.to_pte_flags().bits() as u8)
+}
+
 /// Run the first task in task list.
 pub fn run_first_task() {
     TASK_MANAGER.run_first_task();
@@ -232,9 +572,9 @@ fn mark_current_suspended() {
     TASK_MANAGER.mark_current_suspended();
 }
 
-/// Change the status of current `Running` task into `Exited`.
-fn mark_current_exited() {
-    TASK_MANAGER.mark_current_exited();
+/// Change the status of current `Running` task into `Zombie` or `Exited`.
+fn mark_current_exited(exit_code: i32) {
+    TASK_MANAGER.mark_current_exited(exit_code);
 }
 
 /// Suspend the current 'Running' task and run the next task in task list.
@@ -243,12 +583,53 @@ pub fn suspend_current_and_run_next() {
     run_next_task();
 }
 
+/// Put the current 'Running' task to sleep for `us` microseconds and run the next
+/// task in task list.
+pub fn sleep_current_and_run_next(us: usize) {
+    TASK_MANAGER.mark_current_sleeping(us);
+    run_next_task();
+}
+
 /// Exit the current 'Running' task and run the next task in task list.
-pub fn exit_current_and_run_next() {
-    mark_current_exited();
+pub fn exit_current_and_run_next(exit_code: i32) {
+    mark_current_exited(exit_code);
     run_next_task();
 }
 
+/// Reap an exited child of the current task, returning its pid and exit code,
+/// or -2 if it has not exited yet and -1 if there is no such child.
+pub fn waitpid(pid: isize) -> Result<(usize, i32), isize> {
+    TASK_MANAGER.waitpid(pid)
+}
+
+/// Resolve the copy-on-write pages of the current task in `[start, start + len)`,
+/// which must be done before the kernel writes there.
+pub fn break_current_cow(start: usize, len: usize) {
+    TASK_MANAGER.break_current_cow(start, len)
+}
+
+/// Replace the current task's program with the one in `elf_data`,
+/// returning false if frames run out.
+pub fn exec_current(elf_data: &[u8]) -> bool {
+    TASK_MANAGER.exec_current(elf_data)
+}
+
+/// Fork the current task, returning the pid of the child, or `None` if frames run out.
+pub fn fork_current() -> Option<usize> {
+    TASK_MANAGER.fork_current()
+}
+
+/// Create a child of the current task running `elf_data`, returning its pid,
+/// or `None` if frames run out.
+pub fn spawn_current(elf_data: &[u8]) -> Option<usize> {
+    TASK_MANAGER.spawn_current(elf_data)
+}
+
+/// Get the current 'Running' task's pid.
+pub fn current_pid() -> usize {
+    TASK_MANAGER.get_current_pid()
+}
+
 /// Get the current 'Running' task's token.
 pub fn current_user_token() -> usize {
     TASK_MANAGER.get_current_token()
@@ -259,8 +640,9 @@ pub fn current_trap_cx() -> &'static mut TrapContext {
     TASK_MANAGER.get_current_trap_cx()
 }
 
-pub fn current_task() -> TaskInfo {
-    TASK_MANAGER.get_current_task()
+/// Get a snapshot of the current 'Running' task's info.
+pub fn current_task_info() -> TaskInfo {
+    TASK_MANAGER.get_current_task_info()
 }
 
 
@@ -269,10 +651,80 @@ pub fn update_syscall_times(syscall_id: usize) {
 }
 
 
-pub fn mmap(start_va: VirtAddr, end_va: VirtAddr, port: usize) -> isize {
-    TASK_MANAGER.mmap(start_va, end_va, port)
+pub fn mmap(start_va: VirtAddr, end_va: VirtAddr, port: MmapPort, lazy: bool) -> isize {
+    TASK_MANAGER.mmap(start_va, end_va, port, lazy)
+}
+
+/// Map `len` bytes at an address chosen by the kernel, returning it or -1.
+pub fn mmap_anywhere(len: usize, port: MmapPort, lazy: bool) -> isize {
+    TASK_MANAGER.mmap_anywhere(len, port, lazy)
 }
 
 pub fn munmap(start_va: VirtAddr, end_va: VirtAddr) -> isize {
     TASK_MANAGER.munmap(start_va, end_va)
-}
\ No newline at end of file
+}
+
+pub fn mprotect(start_va: VirtAddr, end_va: VirtAddr, port: MmapPort) -> isize {
+    TASK_MANAGER.mprotect(start_va, end_va, port)
+}
+
+/// Try to resolve a page fault of the current task at `va`, returning false if
+/// the task has to be killed.
+pub fn handle_page_fault(va: VirtAddr) -> bool {
+    TASK_MANAGER.handle_page_fault(va)
+}
+
+/// Whether `va` lies in the stack guard page of the current task, i.e. a fault
+/// there is a stack overflow.
+pub fn in_stack_guard(va: VirtAddr) -> bool {
+    TASK_MANAGER.in_stack_guard(va)
+}
+
+/// Whether `[start, start + len)` is mapped in the current task, checked without
+/// walking the page table so that bad user pointers fail fast.
+pub fn current_range_mapped(start: usize, len: usize) -> bool {
+    TASK_MANAGER.is_current_range_mapped(start, len)
+}
+
+/// Set the scheduling priority of the current task, which must be at least 2.
+pub fn set_priority(priority: usize) {
+    TASK_MANAGER.set_current_priority(priority)
+}
+
+/// Move the current task's program break by `size` bytes, returning the old break.
+pub fn change_program_brk(size: isize) -> Option<usize> {
+    TASK_MANAGER.change_current_program_brk(size)
+}
+
+/// Map the shared memory segment `key` into the current task from `start_va` on.
+pub fn shmat(key: usize, start_va: VirtAddr) -> isize {
+    TASK_MANAGER.shmat(key, start_va)
+}
+
+/// Accessed/dirty status of the current task's pages in `[start_va, end_va)`.
+pub fn page_status(start_va: VirtAddr, end_va: VirtAddr) -> Option<Vec<u8>> {
+    TASK_MANAGER.page_status(start_va, end_va)
+}
+
+/// Clear the accessed/dirty bits of the current task's pages in `[start_va, end_va)`.
+pub fn clear_refs(start_va: VirtAddr, end_va: VirtAddr) -> isize {
+    TASK_MANAGER.clear_refs(start_va, end_va)
+}
+
+/// Flag bits of the page table entry of `va` in the current task, None if not present.
+#[cfg(feature = "test-syscalls")]
+pub fn current_pte_flags(va: VirtAddr) -> Option<usize> {
+    TASK_MANAGER.pte_flags(va)
+}
+
+#[allow(unused)]
+/// apps are loaded without parents and with distinct pids, counting up from the init task
+pub fn task_pids_test() {
+    let inner = TASK_MANAGER.inner.exclusive_access();
+    for (app_id, task) in inner.tasks.iter().enumerate() {
+        assert_eq!(task.pid, IDLE_PID + 1 + app_id);
+        assert!(task.parent.is_none());
+    }
+    assert_eq!(inner.tasks[0].pid, INIT_PID);
+    info!("task_pids_test passed!");
+}
@@ -0,0 +1,74 @@
+//! Allocation of process identifiers
+
+use crate::sync::UPSafeCell;
+use alloc::vec::Vec;
+use lazy_static::*;
+
+/// pid of the idle task, which is never handed out to an app
+pub const IDLE_PID: usize = 0;
+
+/// pid of the init task, the first app loaded, which adopts orphaned tasks
+pub const INIT_PID: usize = IDLE_PID + 1;
+
+/// pid allocator that hands out increasing pids and reuses the released ones
+pub struct PidAllocator {
+    current: usize,
+    recycled: Vec<usize>,
+}
+
+impl PidAllocator {
+    pub fn new() -> Self {
+        Self {
+            current: IDLE_PID + 1,
+            recycled: Vec::new(),
+        }
+    }
+    pub fn alloc(&mut self) -> usize {
+        if let Some(pid) = self.recycled.pop() {
+            pid
+        } else {
+            self.current += 1;
+            self.current - 1
+        }
+    }
+    pub fn dealloc(&mut self, pid: usize) {
+        // validity check
+        if pid == IDLE_PID || pid >= self.current {
+            panic!("Pid {} has not been allocated!", pid);
+        }
+        if self.recycled.iter().any(|p| *p == pid) {
+            panic!("Pid {} is freed twice!", pid);
+        }
+        self.recycled.push(pid);
+    }
+}
+
+lazy_static! {
+    /// pid allocator instance through lazy_static!
+    static ref PID_ALLOCATOR: UPSafeCell<PidAllocator> =
+        unsafe { UPSafeCell::new(PidAllocator::new()) };
+}
+
+/// allocate a pid for a new task
+pub fn pid_alloc() -> usize {
+    PID_ALLOCATOR.exclusive_access().alloc()
+}
+
+/// release the pid of an exited task so that it can be reused
+pub fn pid_dealloc(pid: usize) {
+    PID_ALLOCATOR.exclusive_access().dealloc(pid);
+}
+
+#[allow(unused)]
+/// a simple test for pid allocator, on its own instance to leave the global one untouched
+pub fn pid_allocator_test() {
+    let mut allocator = PidAllocator::new();
+    let first = allocator.alloc();
+    let second = allocator.alloc();
+    assert_eq!(first, IDLE_PID + 1);
+    assert_ne!(first, second);
+    allocator.dealloc(first);
+    assert_eq!(allocator.alloc(), first);
+    assert_eq!(allocator.alloc(), second + 1);
+    info!("pid_allocator_test passed!");
+}
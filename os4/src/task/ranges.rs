@@ -0,0 +1,111 @@
+//! Page ranges mapped in a user address space, kept beside the page table so
+//! that user pointers can be rejected without walking it.
+
+use crate::mm::{VirtAddr, VirtPageNum};
+use alloc::collections::BTreeMap;
+use alloc::vec::Vec;
+
+/// disjoint page ranges `[start, end)` indexed by their start
+#[derive(Clone, Default)]
+pub struct MappedRanges {
+    ranges: BTreeMap<VirtPageNum, VirtPageNum>,
+}
+
+impl MappedRanges {
+    pub fn new() -> Self {
+        Self::default()
+    }
+    /// Record `[start, end)`, which must not overlap a recorded range.
+    pub fn insert(&mut self, start: VirtPageNum, end: VirtPageNum) {
+        if start < end {
+            self.ranges.insert(start, end);
+        }
+    }
+    /// Forget `[start, end)`, splitting the ranges it cuts through.
+    pub fn remove(&mut self, start: VirtPageNum, end: VirtPageNum) {
+        if start >= end {
+            return;
+        }
+        // the range starting before `start` may stick out on both sides
+        if let Some((&head_start, &head_end)) = self.ranges.range(..start).next_back() {
+            if head_end > start {
+                self.ranges.insert(head_start, start);
+                if head_end > end {
+                    self.ranges.insert(end, head_end);
+                }
+            }
+        }
+        let inside: Vec<_> = self
+            .ranges
+            .range(start..end)
+            .map(|(&range_start, &range_end)| (range_start, range_end))
+            .collect();
+        for (range_start, range_end) in inside {
+            self.ranges.remove(&range_start);
+            if range_end > end {
+                self.ranges.insert(end, range_end);
+            }
+        }
+    }
+    /// Forget every range.
+    pub fn clear(&mut self) {
+        self.ranges.clear();
+    }
+    /// Whether every page of `[start, start + len)` lies in a recorded range.
+    /// An empty range is always mapped.
+    pub fn contains(&self, start: usize, len: usize) -> bool {
+        if len == 0 {
+            return true;
+        }
+        let end = match start.checked_add(len) {
+            Some(end) => VirtAddr::from(end).ceil(),
+            None => return false,
+        };
+        let mut cursor = VirtAddr::from(start).floor();
+        // ranges may be adjacent, follow them until `end` is covered
+        while cursor < end {
+            match self.ranges.range(..=cursor).next_back() {
+                Some((_, &range_end)) if range_end > cursor => cursor = range_end,
+                _ => return false,
+            }
+        }
+        true
+    }
+}
+
+#[allow(unused)]
+/// holes punched into a fragmented address space are reported as unmapped
+pub fn mapped_ranges_test() {
+    let page = |n: usize| VirtPageNum::from(n);
+    let addr = |n: usize| usize::from(VirtAddr::from(page(n)));
+    let mut ranges = MappedRanges::new();
+    ranges.insert(page(0x10), page(0x20));
+    ranges.insert(page(0x20), page(0x28));
+    ranges.insert(page(0x40), page(0x50));
+    // adjacent ranges cover a buffer across their border
+    assert!(ranges.contains(addr(0x1f) + 8, 0x1000));
+    assert!(ranges.contains(addr(0x10), addr(0x28) - addr(0x10)));
+    assert!(!ranges.contains(addr(0x27), 0x1001));
+    assert!(!ranges.contains(addr(0x30), 1));
+    assert!(ranges.contains(addr(0x30), 0));
+    assert!(!ranges.contains(usize::MAX, 2));
+    // punch holes into the middle and across a border
+    ranges.remove(page(0x14), page(0x16));
+    ranges.remove(page(0x1f), page(0x21));
+    ranges.remove(page(0x4f), page(0x60));
+    assert!(ranges.contains(addr(0x10), addr(0x14) - addr(0x10)));
+    assert!(!ranges.contains(addr(0x13), 0x2000));
+    assert!(!ranges.contains(addr(0x15), 1));
+    assert!(ranges.contains(addr(0x16), addr(0x1f) - addr(0x16)));
+    assert!(!ranges.contains(addr(0x1f), 1));
+    assert!(!ranges.contains(addr(0x20) + 0x800, 1));
+    assert!(ranges.contains(addr(0x21), addr(0x28) - addr(0x21)));
+    assert!(ranges.contains(addr(0x4e), 0x1000));
+    assert!(!ranges.contains(addr(0x4e), 0x1001));
+    // removing an unmapped range changes nothing
+    ranges.remove(page(0x30), page(0x38));
+    assert!(ranges.contains(addr(0x40), 0xe000));
+    ranges.clear();
+    assert!(!ranges.contains(addr(0x40), 1));
+    info!("mapped_ranges_test passed!");
+}
@@ -1,18 +1,74 @@
 //! Types related to task management
-use super::TaskContext;
-use crate::config::{kernel_stack_position, TRAP_CONTEXT, MAX_SYSCALL_NUM};
+use super::{pid_alloc, MappedRanges, TaskContext};
+use crate::config::{kernel_stack_position, BIG_STRIDE, DEFAULT_PRIORITY, TRAP_CONTEXT, MAX_SYSCALL_NUM};
 use crate::mm::{MapPermission, MemorySet, PhysPageNum, VirtAddr, KERNEL_SPACE};
+use crate::timer::get_time_us;
 use crate::trap::{trap_handler, TrapContext};
 
 /// task control block structure
 pub struct TaskControlBlock {
+    pub pid: usize,
+    /// pid of the task that forked this one, `None` for apps loaded at boot or orphans
+    pub parent: Option<usize>,
+    /// exit code kept for the parent while the task is a `Zombie`
+    pub exit_code: i32,
     pub task_status: TaskStatus,
     pub task_cx: TaskContext,
     pub memory_set: MemorySet,
     pub trap_cx_ppn: PhysPageNum,
     pub base_size: usize,
     pub syscall_times: [u32; MAX_SYSCALL_NUM],
-    pub start_time: usize
+    /// start address of the heap, right above the user stack
+    pub heap_bottom: usize,
+    /// current end of the heap, moved by sbrk
+    pub program_brk: usize,
+    /// user ranges of `memory_set`, to check user pointers without walking the page table
+    pub mapped_ranges: MappedRanges,
+    /// time in us when the task was first dispatched, `None` if it has never run
+    pub start_time: Option<usize>,
+    /// scheduling priority, at least 2
+    pub priority: usize,
+    /// added to `stride` on each dispatch, `BIG_STRIDE / priority`
+    pub pass: u64,
+    /// total pass of the dispatches so far, compared with [`stride_less`]
+    pub stride: u64,
+    /// time in us at which a `Sleeping` task becomes `Ready` again
+    pub wake_time: usize,
+    /// `None` once the task has been reaped and nothing runs on the stack any more
+    pub kernel_stack: Option<KernelStack>,
+}
+
+/// kernel stack of the task in slot `id` of the task list, mapped in kernel
+/// space while the stack lives
+pub struct KernelStack {
+    id: usize,
+}
+
+impl KernelStack {
+    /// Map the kernel stack of slot `id`, or return `None` if frames run out.
+    pub fn new(id: usize) -> Option<Self> {
+        let (kernel_stack_bottom, kernel_stack_top) = kernel_stack_position(id);
+        if !KERNEL_SPACE.lock().try_insert_framed_area(
+            kernel_stack_bottom.into(),
+            kernel_stack_top.into(),
+            MapPermission::R | MapPermission::W,
+        ) {
+            return None;
+        }
+        Some(Self { id })
+    }
+    pub fn get_top(&self) -> usize {
+        kernel_stack_position(self.id).1
+    }
+}
+
+impl Drop for KernelStack {
+    fn drop(&mut self) {
+        let (kernel_stack_bottom, _) = kernel_stack_position(self.id);
+        KERNEL_SPACE
+            .lock()
+            .remove_area_with_start_vpn(VirtAddr::from(kernel_stack_bottom).into());
+    }
 }
 
 impl TaskControlBlock {
@@ -22,30 +78,162 @@ impl TaskControlBlock {
     pub fn get_user_token(&self) -> usize {
         self.memory_set.token()
     }
-    pub fn new(elf_data: &[u8], app_id: usize) -> Self {
+    /// Record the first dispatch time; later dispatches keep the original one.
+    pub fn record_first_dispatch(&mut self) {
+        if self.start_time.is_none() {
+            self.start_time = Some(get_time_us());
+        }
+    }
+    /// Milliseconds elapsed since the first dispatch, 0 if the task has never run.
+    pub fn running_time_ms(&self) -> usize {
+        self.start_time
+            .map_or(0, |start_time| (get_time_us() - start_time) / 1000)
+    }
+    /// Change the priority, and with it the pass, of this task.
+    pub fn set_priority(&mut self, priority: usize) {
+        self.priority = priority;
+        self.pass = pass_of(priority);
+    }
+    /// Add the pass of this task to its stride after it has been scheduled.
+    pub fn advance_stride(&mut self) {
+        self.stride = self.stride.wrapping_add(self.pass);
+    }
+    /// Whether every page of `[start, start + len)` is mapped in user space,
+    /// lazy pages included.
+    pub fn is_range_mapped(&self, start: usize, len: usize) -> bool {
+        self.mapped_ranges.contains(start, len)
+    }
+    /// Move the program break by `size` bytes, returning the old break, or `None`
+    /// if the heap would go below its base or cannot grow.
+    pub fn change_program_brk(&mut self, size: isize) -> Option<usize> {
+        let old_break = self.program_brk;
+        let new_brk = self.program_brk as isize + size;
+        if new_brk < self.heap_bottom as isize {
+            return None;
+        }
+        let result = if size < 0 {
+            self.memory_set
+                .shrink_to(VirtAddr(self.heap_bottom), VirtAddr(new_brk as usize))
+        } else {
+            self.memory_set
+                .append_to(VirtAddr(self.heap_bottom), VirtAddr(new_brk as usize))
+        };
+        if result {
+            self.program_brk = new_brk as usize;
+            let heap_bottom = VirtAddr(self.heap_bottom).floor();
+            self.mapped_ranges.remove(heap_bottom, VirtAddr(old_break).ceil());
+            self.mapped_ranges.insert(heap_bottom, VirtAddr(self.program_brk).ceil());
+            Some(old_break)
+        } else {
+            None
+        }
+    }
+    /// Create a child of this task for fork, running on the kernel stack of `app_id`.
+    ///
+    /// The user pages are shared copy-on-write, so writes by either side are
+    /// not seen by the other. The child returns 0 from the syscall.
+    ///
+    /// Returns `None` if frames run out, after releasing what the child got so far.
+    pub fn fork(&mut self, app_id: usize) -> Option<Self> {
+        let memory_set = self.memory_set.clone_cow()?;
+        let trap_cx_ppn = memory_set
+            .translate(VirtAddr::from(TRAP_CONTEXT).into())
+            .unwrap()
+            .ppn();
+        // map a kernel-stack in kernel space
+        let kernel_stack = KernelStack::new(app_id)?;
+        let kernel_stack_top = kernel_stack.get_top();
+        let task_control_block = Self {
+            pid: pid_alloc(),
+            parent: Some(self.pid),
+            exit_code: 0,
+            task_status: TaskStatus::Ready,
+            task_cx: TaskContext::goto_trap_return(kernel_stack_top),
+            memory_set,
+            trap_cx_ppn,
+            base_size: self.base_size,
+            heap_bottom: self.heap_bottom,
+            program_brk: self.program_brk,
+            mapped_ranges: self.mapped_ranges.clone(),
+            start_time: None,
+            syscall_times: [0; MAX_SYSCALL_NUM],
+            priority: self.priority,
+            pass: self.pass,
+            stride: self.stride,
+            wake_time: 0,
+            kernel_stack: Some(kernel_stack),
+        };
+        // the trap context has been copied from the parent
+        let trap_cx = task_control_block.get_trap_cx();
+        trap_cx.kernel_sp = kernel_stack_top;
+        trap_cx.x[10] = 0;
+        Some(task_control_block)
+    }
+    /// Replace the address space of this task with a fresh one built from `elf_data`,
+    /// starting over at its entry point. The old address space is dropped along
+    /// with its frames.
+    ///
+    /// Returns false, keeping the old address space, if frames run out.
+    pub fn exec(&mut self, elf_data: &[u8]) -> bool {
+        let (memory_set, user_sp, entry_point) = match MemorySet::from_elf(elf_data) {
+            Some(result) => result,
+            None => return false,
+        };
+        let trap_cx_ppn = memory_set
+            .translate(VirtAddr::from(TRAP_CONTEXT).into())
+            .unwrap()
+            .ppn();
+        // the kernel stack stays the same
+        let kernel_sp = self.get_trap_cx().kernel_sp;
+        self.mapped_ranges = user_ranges_of(&memory_set);
+        self.memory_set = memory_set;
+        self.trap_cx_ppn = trap_cx_ppn;
+        self.base_size = user_sp;
+        self.heap_bottom = user_sp;
+        self.program_brk = user_sp;
+        *self.get_trap_cx() = TrapContext::app_init_context(
+            entry_point,
+            user_sp,
+            KERNEL_SPACE.lock().token(),
+            kernel_sp,
+            trap_handler as usize,
+        );
+        true
+    }
+    /// Load `elf_data` as a new task running on the kernel stack of `app_id`,
+    /// or return `None` if frames run out.
+    pub fn new(elf_data: &[u8], app_id: usize) -> Option<Self> {
         // memory_set with elf program headers/trampoline/trap context/user stack
-        let (memory_set, user_sp, entry_point) = MemorySet::from_elf(elf_data);
+        let (memory_set, user_sp, entry_point) = MemorySet::from_elf(elf_data)?;
         let trap_cx_ppn = memory_set
             .translate(VirtAddr::from(TRAP_CONTEXT).into())
             .unwrap()
             .ppn();
+        let mapped_ranges = user_ranges_of(&memory_set);
         let task_status = TaskStatus::Ready;
         // map a kernel-stack in kernel space
-        let (kernel_stack_bottom, kernel_stack_top) = kernel_stack_position(app_id);
-        KERNEL_SPACE.lock().insert_framed_area(
-            kernel_stack_bottom.into(),
-            kernel_stack_top.into(),
-            MapPermission::R | MapPermission::W,
-        );
+        let kernel_stack = KernelStack::new(app_id)?;
+        let kernel_stack_top = kernel_stack.get_top();
         
         let task_control_block = Self {
+            pid: pid_alloc(),
+            parent: None,
+            exit_code: 0,
             task_status,
             task_cx: TaskContext::goto_trap_return(kernel_stack_top),
             memory_set,
             trap_cx_ppn,
             base_size: user_sp,
-            start_time: 0,
+            heap_bottom: user_sp,
+            program_brk: user_sp,
+            mapped_ranges,
+            start_time: None,
             syscall_times: [0; MAX_SYSCALL_NUM],
+            priority: DEFAULT_PRIORITY,
+            pass: pass_of(DEFAULT_PRIORITY),
+            stride: 0,
+            wake_time: 0,
+            kernel_stack: Some(kernel_stack),
         };
         // prepare TrapContext in user space
         let trap_cx = task_control_block.get_trap_cx();
@@ -56,15 +244,72 @@ impl TaskControlBlock {
             kernel_stack_top,
             trap_handler as usize,
         );
-        task_control_block
+        Some(task_control_block)
+    }
+}
+
+/// Pass of a task with `priority`.
+fn pass_of(priority: usize) -> u64 {
+    // a priority above BIG_STRIDE still has to move the stride forward
+    (BIG_STRIDE / priority as u64).max(1)
+}
+
+/// Whether stride `a` is behind stride `b`, taking wraparound into account.
+///
+/// With priorities of at least 2, a pass is at most `BIG_STRIDE / 2`, so the
+/// strides of ready tasks are never further apart than that and their wrapped
+/// difference read as `i64` has the right sign.
+pub fn stride_less(a: u64, b: u64) -> bool {
+    (a.wrapping_sub(b) as i64) < 0
+}
+
+/// Record the user ranges of a freshly built address space.
+fn user_ranges_of(memory_set: &MemorySet) -> MappedRanges {
+    let mut mapped_ranges = MappedRanges::new();
+    for (start, end) in memory_set.user_ranges() {
+        mapped_ranges.insert(start, end);
     }
+    mapped_ranges
 }
 
 #[derive(Copy, Clone, PartialEq)]
-/// task status: UnInit, Ready, Running, Exited
+/// task status: UnInit, Ready, Running, Exited, Zombie, Sleeping
 pub enum TaskStatus {
     UnInit,
     Ready,
     Running,
     Exited,
+    /// exited, but the exit code has not been collected by the parent yet
+    Zombie,
+    /// waiting for its `wake_time`, never scheduled before that
+    Sleeping,
+}
+
+#[allow(unused)]
+/// strides keep their order across wraparound, and priority 4 is picked twice as often as 2
+pub fn stride_test() {
+    assert!(stride_less(1, 2));
+    assert!(!stride_less(2, 1));
+    assert!(!stride_less(7, 7));
+    let passes = [pass_of(2), pass_of(4)];
+    assert_eq!(passes, [BIG_STRIDE / 2, BIG_STRIDE / 4]);
+    assert_eq!(pass_of(usize::MAX), 1);
+    // two tasks just below the wrap point, the first one wraps around to a
+    // small stride once dispatched but is still ahead of the second one
+    let mut strides = [u64::MAX - 10, u64::MAX - 5];
+    assert!(stride_less(strides[0], strides[1]));
+    strides[0] = strides[0].wrapping_add(passes[0]);
+    assert!(strides[0] < strides[1]);
+    assert!(stride_less(strides[1], strides[0]));
+    strides[1] = strides[1].wrapping_add(passes[1]);
+    assert!(stride_less(strides[1], strides[0]));
+    let mut strides = [u64::MAX - 1000; 2];
+    let mut picked = [0usize; 2];
+    for _ in 0..300 {
+        let next = if stride_less(strides[1], strides[0]) { 1 } else { 0 };
+        picked[next] += 1;
+        strides[next] = strides[next].wrapping_add(passes[next]);
+    }
+    assert_eq!(picked, [100, 200]);
+    info!("stride_test passed!");
 }
@@ -1,18 +1,141 @@
 use crate::config::CLOCK_FREQ;
 use crate::sbi::set_timer;
+use crate::sync::UPSafeCell;
+use lazy_static::*;
 use riscv::register::time;
 
 const TICKS_PER_SEC: usize = 100;
+const MSEC_PER_SEC: usize = 1000;
 const MICRO_PER_SEC: usize = 1_000_000;
+const NANO_PER_SEC: usize = 1_000_000_000;
 
 pub fn get_time() -> usize {
     time::read()
 }
 
+/// time since the raw timer started in ms, read from the same clock as `get_time_ns`
+pub fn get_time_ms() -> usize {
+    get_time_ns() / (NANO_PER_SEC / MSEC_PER_SEC)
+}
+
+/// time since the raw timer started in us, read from the same clock as `get_time_ns`
 pub fn get_time_us() -> usize {
-    time::read() / (CLOCK_FREQ / MICRO_PER_SEC)
+    get_time_ns() / (NANO_PER_SEC / MICRO_PER_SEC)
+}
+
+/// time since the raw timer started in ns, as precise as the timer frequency
+/// allows, which keeps increasing even if the raw timer rolls over
+pub fn get_time_ns() -> usize {
+    UPTIME_CLOCK.exclusive_access().now_ns(time::read() as u64) as usize
+}
+
+/// a clock that never goes backwards, even if the raw timer is reset
+struct MonotonicClock {
+    /// raw time at the last reading
+    last_raw: usize,
+    /// time accumulated before the raw timer was last reset
+    base: usize,
+}
+
+impl MonotonicClock {
+    fn new() -> Self {
+        Self {
+            last_raw: 0,
+            base: 0,
+        }
+    }
+    fn now(&mut self, raw: usize) -> usize {
+        if raw < self.last_raw {
+            // the raw timer has been reset, keep counting from where it stopped
+            self.base += self.last_raw;
+        }
+        self.last_raw = raw;
+        self.base + raw
+    }
+}
+
+/// nanoseconds since boot, accumulated from the ticks elapsed between two
+/// readings so that a rollover of the raw counter does not move it back
+struct UptimeClock {
+    /// raw ticks at the last reading
+    last_ticks: u64,
+    /// elapsed ticks times NANO_PER_SEC not yet making up a whole nanosecond
+    rem: u64,
+    ns: u64,
+}
+
+impl UptimeClock {
+    fn new(ticks: u64) -> Self {
+        Self {
+            last_ticks: ticks,
+            rem: 0,
+            ns: 0,
+        }
+    }
+    fn now_ns(&mut self, ticks: u64) -> u64 {
+        let freq = CLOCK_FREQ as u64;
+        let nano = NANO_PER_SEC as u64;
+        // correct across a rollover as long as it is read once per 2^64 ticks
+        let delta = ticks.wrapping_sub(self.last_ticks);
+        self.last_ticks = ticks;
+        // split the ticks so that the product does not overflow
+        let frac = delta % freq * nano + self.rem;
+        self.ns += delta / freq * nano + frac / freq;
+        self.rem = frac % freq;
+        self.ns
+    }
+}
+
+lazy_static! {
+    static ref MONOTONIC_CLOCK: UPSafeCell<MonotonicClock> =
+        unsafe { UPSafeCell::new(MonotonicClock::new()) };
+    static ref UPTIME_CLOCK: UPSafeCell<UptimeClock> =
+        unsafe { UPSafeCell::new(UptimeClock::new(0)) };
+}
+
+/// monotonic time in ns
+pub fn get_monotonic_time_ns() -> usize {
+    MONOTONIC_CLOCK.exclusive_access().now(get_time_ns())
 }
 
 pub fn set_next_trigger() {
     set_timer(get_time() + CLOCK_FREQ / TICKS_PER_SEC);
 }
+
+#[allow(unused)]
+/// the monotonic clock keeps going up across a reset of the raw timer
+pub fn monotonic_clock_test() {
+    let mut clock = MonotonicClock::new();
+    assert_eq!(clock.now(100), 100);
+    assert_eq!(clock.now(250), 250);
+    // reset
+    assert_eq!(clock.now(10), 260);
+    assert_eq!(clock.now(40), 290);
+    info!("monotonic_clock_test passed!");
+}
+
+#[allow(unused)]
+/// the uptime clock converts ticks without drift and keeps going up when the raw timer wraps
+pub fn uptime_clock_test() {
+    let freq = CLOCK_FREQ as u64;
+    let mut clock = UptimeClock::new(0);
+    assert_eq!(clock.now_ns(freq), 1_000_000_000);
+    // reading tick by tick for 1ms does not drift
+    let mut ns = 0;
+    for ticks in (freq + 1)..=(freq + freq / 1000) {
+        ns = clock.now_ns(ticks);
+    }
+    assert_eq!(ns, 1_001_000_000);
+    // the raw timer wraps half a second after the first reading
+    let start = u64::MAX - freq / 2 + 1;
+    let mut clock = UptimeClock::new(start);
+    let mut last_sec = 0;
+    for ticks in [start + freq / 4, 0, freq / 2, 3 * freq] {
+        let sec = clock.now_ns(ticks) / NANO_PER_SEC as u64;
+        assert!(sec >= last_sec);
+        last_sec = sec;
+    }
+    assert_eq!(clock.now_ns(3 * freq), 3_500_000_000);
+    assert_eq!(last_sec, 3);
+    info!("uptime_clock_test passed!");
+}
@@ -0,0 +1,35 @@
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user_lib;
+
+use user_lib::{mmap, sys_get_time, task_info, write, TaskInfo, TimeVal, STDOUT};
+
+/*
+理想结果：向内核传入未映射、只读或指向内核空间的指针时返回 -1 而不是导致内核 panic，
+输出 Test bad pointer OK!
+*/
+
+#[no_mangle]
+fn main() -> i32 {
+    let bad: usize = 0x10000000;
+    let time = unsafe { &*(bad as *const TimeVal) };
+    assert_eq!(-1, sys_get_time(time, 0));
+    let info = unsafe { &*(bad as *const TaskInfo) };
+    assert_eq!(-1, task_info(info));
+    let buf = unsafe { core::slice::from_raw_parts(bad as *const u8, 16) };
+    assert_eq!(-1, write(STDOUT, buf));
+    // 只读页不能写回结果
+    assert_eq!(0, mmap(bad, 4096, 1));
+    assert_eq!(-1, sys_get_time(time, 0));
+    assert_eq!(-1, task_info(info));
+    // 内核的地址
+    let kernel: usize = 0x80200000;
+    let time = unsafe { &*(kernel as *const TimeVal) };
+    assert_eq!(-1, sys_get_time(time, 0));
+    let info = unsafe { &*(kernel as *const TaskInfo) };
+    assert_eq!(-1, task_info(info));
+    println!("Test bad pointer OK!");
+    0
+}
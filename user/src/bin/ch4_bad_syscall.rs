@@ -0,0 +1,23 @@
+#![no_std]
+#![no_main]
+
+extern crate user_lib;
+
+use user_lib::{println, syscall, task_info, TaskInfo, SYSCALL_TASK_INFO};
+
+/*
+理想结果：未知的 syscall id（包括超出 MAX_SYSCALL_NUM 的）返回 -1 且不影响计数，输出 Test bad syscall OK!
+*/
+
+#[no_mangle]
+pub fn main() -> usize {
+    assert_eq!(-1, syscall(499, [0, 0, 0]));
+    assert_eq!(-1, syscall(500, [0, 0, 0]));
+    assert_eq!(-1, syscall(usize::MAX, [0, 0, 0]));
+    let info = TaskInfo::new();
+    assert_eq!(0, task_info(&info));
+    assert_eq!(1, info.syscall_times[SYSCALL_TASK_INFO]);
+    assert_eq!(1, info.syscall_times[499]);
+    println!("Test bad syscall OK!");
+    0
+}
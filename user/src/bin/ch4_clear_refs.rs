@@ -0,0 +1,40 @@
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user_lib;
+
+use user_lib::{clear_refs, mmap, munmap, page_status};
+
+/*
+理想结果：clear_refs 之后所有页的状态为 0，再次访问的页重新被置位，
+区间内有未映射的页时返回 -1，输出 Test clear refs OK!
+*/
+
+#[no_mangle]
+fn main() -> i32 {
+    let start: usize = 0x10000000;
+    let len: usize = 4096;
+    let prot: usize = 3;
+    let mut status = [0xffu8; 2];
+    assert_eq!(0, mmap(start, len * 2, prot));
+    unsafe {
+        (start as *mut u8).write_volatile(1);
+        ((start + len) as *mut u8).write_volatile(1);
+    }
+    assert_eq!(0, page_status(start, len * 2, &mut status));
+    assert_eq!(status, [0b11, 0b11]);
+    assert_eq!(0, clear_refs(start, len * 2));
+    assert_eq!(0, page_status(start, len * 2, &mut status));
+    assert_eq!(status, [0b00, 0b00]);
+    // 只有再次访问的页被硬件重新置位
+    unsafe {
+        assert_eq!(1, ((start + len) as *const u8).read_volatile());
+    }
+    assert_eq!(0, page_status(start, len * 2, &mut status));
+    assert_eq!(status, [0b00, 0b01]);
+    assert_eq!(-1, clear_refs(start, len * 3));
+    assert_eq!(0, munmap(start, len * 2));
+    println!("Test clear refs OK!");
+    0
+}
@@ -0,0 +1,45 @@
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user_lib;
+
+use user_lib::{clock_gettime, sys_get_time, TimeSpec, TimeVal, CLOCK_MONOTONIC, CLOCK_REALTIME};
+
+/*
+理想结果：两种时钟都可读取且单调时钟不倒退，纳秒部分小于一秒，不存在的时钟返回 -1，
+get_time 与 CLOCK_REALTIME 读取同一个时钟，
+输出 Test clock_gettime OK!
+*/
+
+fn to_ns(time: &TimeSpec) -> usize {
+    assert!(time.nsec < 1_000_000_000);
+    time.sec * 1_000_000_000 + time.nsec
+}
+
+#[no_mangle]
+fn main() -> i32 {
+    let time = TimeSpec::default();
+    assert_eq!(0, clock_gettime(CLOCK_REALTIME, &time));
+    assert!(to_ns(&time) > 0);
+    assert_eq!(0, clock_gettime(CLOCK_MONOTONIC, &time));
+    let mut last = to_ns(&time);
+    for _ in 0..1000 {
+        assert_eq!(0, clock_gettime(CLOCK_MONOTONIC, &time));
+        let now = to_ns(&time);
+        assert!(now >= last);
+        last = now;
+    }
+    assert_eq!(-1, clock_gettime(2, &time));
+    // get_time 读到的时间落在前后两次 CLOCK_REALTIME 之间
+    let val = TimeVal::new();
+    assert_eq!(0, clock_gettime(CLOCK_REALTIME, &time));
+    let before = to_ns(&time) / 1000;
+    assert_eq!(0, sys_get_time(&val, 0));
+    assert_eq!(0, clock_gettime(CLOCK_REALTIME, &time));
+    let after = to_ns(&time) / 1000;
+    let us = val.sec * 1_000_000 + val.usec;
+    assert!(before <= us && us <= after);
+    println!("Test clock_gettime OK!");
+    0
+}
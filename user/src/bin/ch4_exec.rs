@@ -0,0 +1,37 @@
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user_lib;
+
+use user_lib::{exec, fork, mmap, yield_};
+
+/*
+理想结果：不存在的程序 exec 返回 -1；fork 出的子进程 exec 后运行 ch4_exec_target，
+输出 Test exec target OK! 与 Test exec OK!
+*/
+
+#[no_mangle]
+fn main() -> i32 {
+    assert_eq!(-1, exec("ch4_no_such_app\0", &[core::ptr::null::<u8>()]));
+    // 失败的 exec 不影响当前地址空间
+    let start: usize = 0x10000000;
+    assert_eq!(0, mmap(start, 4096, 3));
+    unsafe {
+        (start as *mut u8).write_volatile(42);
+    }
+    let pid = fork();
+    if pid == 0 {
+        exec("ch4_exec_target\0", &[core::ptr::null::<u8>()]);
+        panic!("FAIL: exec returned");
+    }
+    assert!(pid > 0);
+    for _ in 0..10 {
+        yield_();
+    }
+    unsafe {
+        assert_eq!(42, (start as *const u8).read_volatile());
+    }
+    println!("Test exec OK!");
+    0
+}
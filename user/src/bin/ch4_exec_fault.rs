@@ -0,0 +1,44 @@
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user_lib;
+
+use user_lib::{exit, fork, mmap, mprotect, waitpid};
+
+/*
+理想结果：跳转到未映射的地址、不可执行的页或被 mprotect 去掉执行权限的页时
+只有该进程被杀死（退出码 -2），
+内核不会 panic，输出 Test exec fault OK!
+*/
+
+const PAGE: usize = 4096;
+
+/// 在子进程中跳转到 addr，返回子进程的退出码
+fn jump_in_child(addr: usize) -> i32 {
+    let pid = fork();
+    if pid == 0 {
+        let f: fn() = unsafe { core::mem::transmute(addr) };
+        f();
+        exit(0);
+    }
+    let mut exit_code: i32 = 0;
+    assert_eq!(pid, waitpid(pid as usize, &mut exit_code));
+    exit_code
+}
+
+#[no_mangle]
+fn main() -> i32 {
+    let start: usize = 0x10000000;
+    // 未映射的页
+    assert_eq!(jump_in_child(start), -2);
+    // 可读写但不可执行的页
+    assert_eq!(0, mmap(start, PAGE, 0b011));
+    assert_eq!(jump_in_child(start), -2);
+    // 去掉执行权限后的页
+    assert_eq!(0, mmap(start + PAGE, PAGE, 0b101));
+    assert_eq!(0, mprotect(start + PAGE, PAGE, 0b001));
+    assert_eq!(jump_in_child(start + PAGE), -2);
+    println!("Test exec fault OK!");
+    0
+}
@@ -0,0 +1,22 @@
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user_lib;
+
+use user_lib::sbrk;
+
+/*
+辅助测例：被 ch4_exec 通过 exec 载入，也会作为普通程序单独运行
+理想结果：全新的地址空间中堆为空，输出 Test exec target OK!
+*/
+
+#[no_mangle]
+pub fn main() -> i32 {
+    let heap_bottom = sbrk(0);
+    assert!(heap_bottom > 0);
+    // 新地址空间中 exec 之前的映射不复存在
+    assert_eq!(heap_bottom, sbrk(0));
+    println!("Test exec target OK!");
+    0
+}
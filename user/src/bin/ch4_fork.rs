@@ -0,0 +1,54 @@
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user_lib;
+
+use user_lib::{exit, fork, getpid, mmap, yield_};
+
+/*
+理想结果：fork 后父子进程向同一虚拟地址写入不同的值，各自只看到自己写入的值，
+输出 Test fork child OK! 与 Test fork OK!
+*/
+
+#[no_mangle]
+fn main() -> i32 {
+    let start: usize = 0x10000000;
+    let len: usize = 4096;
+    let prot: usize = 3;
+    assert_eq!(0, mmap(start, len, prot));
+    let slot = start as *mut usize;
+    unsafe {
+        slot.write_volatile(1);
+    }
+    let parent_pid = getpid();
+    let pid = fork();
+    if pid == 0 {
+        // 子进程
+        assert_ne!(getpid(), parent_pid);
+        unsafe {
+            assert_eq!(1, slot.read_volatile());
+            slot.write_volatile(2);
+        }
+        for _ in 0..10 {
+            yield_();
+            unsafe {
+                assert_eq!(2, slot.read_volatile());
+            }
+        }
+        println!("Test fork child OK!");
+        exit(0);
+    }
+    assert!(pid > parent_pid);
+    unsafe {
+        slot.write_volatile(3);
+    }
+    for _ in 0..10 {
+        yield_();
+        unsafe {
+            assert_eq!(3, slot.read_volatile());
+        }
+    }
+    println!("Test fork OK!");
+    0
+}
@@ -0,0 +1,57 @@
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user_lib;
+
+use user_lib::{exit, fork, mmap, waitpid};
+
+/*
+理想结果：fork 后父子进程以写时复制的方式共享页，子进程写入的每一页都会被复制，
+子进程退出后父进程的数据保持不变，输出 Test fork cow OK!
+*/
+
+const PAGE: usize = 4096;
+const PAGES: usize = 4;
+
+#[no_mangle]
+fn main() -> i32 {
+    let start: usize = 0x10000000;
+    let prot: usize = 3;
+    assert_eq!(0, mmap(start, PAGE * PAGES, prot));
+    for page in 0..PAGES {
+        let addr = (start + page * PAGE) as *mut usize;
+        unsafe {
+            addr.write_volatile(page + 100);
+        }
+    }
+    let mut local: usize = 7;
+    let pid = fork();
+    if pid == 0 {
+        for page in 0..PAGES {
+            let addr = (start + page * PAGE) as *mut usize;
+            unsafe {
+                assert_eq!(page + 100, addr.read_volatile());
+                addr.write_volatile(page + 200);
+                assert_eq!(page + 200, addr.read_volatile());
+            }
+        }
+        // 栈上的变量同样是写时复制的
+        unsafe {
+            (&mut local as *mut usize).write_volatile(8);
+        }
+        exit(0);
+    }
+    let mut exit_code: i32 = -1;
+    assert_eq!(pid, waitpid(pid as usize, &mut exit_code));
+    assert_eq!(0, exit_code);
+    for page in 0..PAGES {
+        let addr = (start + page * PAGE) as *const usize;
+        unsafe {
+            assert_eq!(page + 100, addr.read_volatile());
+        }
+    }
+    assert_eq!(7, unsafe { (&local as *const usize).read_volatile() });
+    println!("Test fork cow OK!");
+    0
+}
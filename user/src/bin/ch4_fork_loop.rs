@@ -0,0 +1,41 @@
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user_lib;
+
+use user_lib::{exit, fork, frame_stats, wait, FrameStats};
+
+/*
+理想结果：反复 fork 并回收子进程时，子进程的内核栈与任务槽位被复用，
+已用页帧不会随次数增长，输出 Test fork loop OK!
+*/
+
+const ROUNDS: usize = 500;
+/// 允许其他任务在测试期间多占用的页帧数，小于一个内核栈
+const SLACK: usize = 16;
+
+fn fork_and_wait() {
+    if fork() == 0 {
+        exit(0);
+    }
+    let mut exit_code: i32 = -1;
+    assert!(wait(&mut exit_code) > 0);
+    assert_eq!(exit_code, 0);
+}
+
+#[no_mangle]
+fn main() -> i32 {
+    fork_and_wait();
+    let mut before = FrameStats::default();
+    assert_eq!(0, frame_stats(&mut before));
+    for _ in 0..ROUNDS {
+        fork_and_wait();
+    }
+    let mut after = FrameStats::default();
+    assert_eq!(0, frame_stats(&mut after));
+    // 每个内核栈占 20 页，泄漏哪怕一个也会超出余量；余量只留给同时运行的其他任务，不随 ROUNDS 增长
+    assert!(after.used <= before.used + SLACK);
+    println!("Test fork loop OK!");
+    0
+}
@@ -0,0 +1,44 @@
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user_lib;
+
+use user_lib::{frame_stats, mmap, munmap, FrameStats};
+
+/*
+理想结果：页帧统计满足 used + free == total，mmap 16 页并写入后已用页帧增加，
+munmap 后回到原值（其他任务不分配页帧时），输出 Test frame stats OK!
+*/
+
+const PAGE: usize = 4096;
+
+#[no_mangle]
+fn main() -> i32 {
+    let mut before = FrameStats::default();
+    assert_eq!(0, frame_stats(&mut before));
+    assert_eq!(before.total, before.used + before.free);
+    let start: usize = 0x10000000;
+    assert_eq!(0, mmap(start, PAGE * 16, 3));
+    for page in 0..16 {
+        unsafe {
+            ((start + page * PAGE) as *mut usize).write_volatile(page);
+        }
+    }
+    let mut mapped = FrameStats::default();
+    assert_eq!(0, frame_stats(&mut mapped));
+    assert_eq!(mapped.total, before.total);
+    assert!(mapped.used >= before.used + 16);
+    assert_eq!(0, munmap(start, PAGE * 16));
+    let mut after = FrameStats::default();
+    assert_eq!(0, frame_stats(&mut after));
+    println!(
+        "frames used: {} before mmap, {} mapped, {} after munmap",
+        before.used, mapped.used, after.used
+    );
+    assert!(after.used + 16 <= mapped.used);
+    // 不可写的地址
+    assert_eq!(-1, frame_stats(unsafe { &mut *(0x80200000 as *mut FrameStats) }));
+    println!("Test frame stats OK!");
+    0
+}
@@ -0,0 +1,29 @@
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user_lib;
+
+use user_lib::{mmap, sys_get_time, TimeVal};
+
+/*
+理想结果：TimeVal 跨越页边界时 get_time 仍然正确写入，输出 Test get_time cross page OK!
+*/
+
+#[no_mangle]
+fn main() -> i32 {
+    let start: usize = 0x10000000;
+    let len: usize = 4096 * 2;
+    let prot: usize = 3;
+    assert_eq!(0, mmap(start, len, prot));
+    // sec 落在第一页末尾，usec 落在第二页开头
+    let addr = start + 4096 - core::mem::size_of::<usize>();
+    let time = unsafe { &*(addr as *const TimeVal) };
+    assert_eq!(0, sys_get_time(time, 0));
+    let first = (time.sec, time.usec);
+    assert!(time.usec < 1_000_000);
+    assert_eq!(0, sys_get_time(time, 0));
+    assert!((time.sec, time.usec) >= first);
+    println!("Test get_time cross page OK!");
+    0
+}
@@ -0,0 +1,43 @@
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user_lib;
+
+use user_lib::{mmap, sys_get_time, TimeVal};
+
+/*
+理想结果：TimeVal 恰好位于页末尾或页开头时 get_time 正确写入且不改动相邻的字节，
+输出 Test get_time page edge OK!
+*/
+
+#[no_mangle]
+fn main() -> i32 {
+    let start: usize = 0x10000000;
+    let len: usize = 4096 * 2;
+    let prot: usize = 3;
+    assert_eq!(0, mmap(start, len, prot));
+    let size = core::mem::size_of::<TimeVal>();
+    // 结束于第一页的最后一个字节，以及开始于第二页的第一个字节
+    for addr in [start + 4096 - size, start + 4096] {
+        let guard_before = (addr - 1) as *mut u8;
+        let guard_after = (addr + size) as *mut u8;
+        unsafe {
+            guard_before.write_volatile(0xaa);
+            guard_after.write_volatile(0x55);
+        }
+        let time = unsafe { &*(addr as *const TimeVal) };
+        assert_eq!(0, sys_get_time(time, 0));
+        let first = (time.sec, time.usec);
+        assert!(first != (0, 0));
+        assert!(time.usec < 1_000_000);
+        assert_eq!(0, sys_get_time(time, 0));
+        assert!((time.sec, time.usec) >= first);
+        unsafe {
+            assert_eq!(0xaa, guard_before.read_volatile());
+            assert_eq!(0x55, guard_after.read_volatile());
+        }
+    }
+    println!("Test get_time page edge OK!");
+    0
+}
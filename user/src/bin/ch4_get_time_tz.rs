@@ -0,0 +1,35 @@
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user_lib;
+
+use user_lib::{mmap, sys_get_time, TimeVal, TimeZone};
+
+/*
+理想结果：tz 为 0 时不写时区；tz 有效时写回 UTC 时区；tz 未映射时返回 -1，
+输出 Test get_time timezone OK!
+*/
+
+#[no_mangle]
+fn main() -> i32 {
+    let time = TimeVal::new();
+    assert_eq!(0, sys_get_time(&time, 0));
+    let start: usize = 0x10000000;
+    assert_eq!(0, mmap(start, 4096, 3));
+    let tz = start as *mut TimeZone;
+    unsafe {
+        tz.write_volatile(TimeZone {
+            minuteswest: 123,
+            dsttime: 456,
+        });
+    }
+    assert_eq!(0, sys_get_time(&time, tz as usize));
+    unsafe {
+        assert_eq!(0, (*tz).minuteswest);
+        assert_eq!(0, (*tz).dsttime);
+    }
+    assert_eq!(-1, sys_get_time(&time, start + 4096));
+    println!("Test get_time timezone OK!");
+    0
+}
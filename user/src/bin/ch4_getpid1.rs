@@ -0,0 +1,20 @@
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user_lib;
+
+use user_lib::getpid;
+
+/*
+理想结果：pid 从 1 开始（0 留给 idle 任务），且与 ch4_getpid0/ch4_getpid1 中另一个输出的 pid 不同
+*/
+
+#[no_mangle]
+pub fn main() -> i32 {
+    let pid = getpid();
+    assert!(pid >= 1);
+    assert_eq!(pid, getpid());
+    println!("Test getpid OK! pid = {}", pid);
+    0
+}
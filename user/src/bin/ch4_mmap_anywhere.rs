@@ -0,0 +1,40 @@
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user_lib;
+
+use user_lib::{mmap, munmap};
+
+/*
+理想结果：start 为 0 时内核按首次适配选择空闲的页对齐地址并返回，释放后的空隙会被再次使用，
+输出 Test mmap anywhere OK!
+*/
+
+#[no_mangle]
+fn main() -> i32 {
+    let len: usize = 4096;
+    let prot: usize = 3;
+    assert_eq!(-1, mmap(0, 0, prot));
+    let first = mmap(0, len * 3, prot);
+    assert!(first > 0);
+    let first = first as usize;
+    assert_eq!(0, first % len);
+    for i in first..(first + len * 3) {
+        let addr: *mut u8 = i as *mut u8;
+        unsafe {
+            *addr = i as u8;
+        }
+    }
+    // 选出的区域已被占用
+    assert_eq!(-1, mmap(first, len, prot));
+    let second = mmap(0, len, prot);
+    assert!(second as usize >= first + len * 3);
+    // 首次适配：释放第一段后，较小的请求落回这里
+    assert_eq!(0, munmap(first, len * 3));
+    assert_eq!(first as isize, mmap(0, len * 2, prot));
+    assert_eq!(0, munmap(first, len * 2));
+    assert_eq!(0, munmap(second as usize, len));
+    println!("Test mmap anywhere OK!");
+    0
+}
@@ -0,0 +1,33 @@
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user_lib;
+
+use user_lib::{mmap, munmap};
+
+/*
+理想结果：高位不是第 38 位符号扩展的地址（非规范地址）会与低 39 位相同的地址重叠，
+mmap 与 munmap 都返回 -1，输出 Test mmap canonical OK!
+*/
+
+const PAGE: usize = 4096;
+
+#[no_mangle]
+fn main() -> i32 {
+    let start: usize = 0x10000000;
+    let prot: usize = 3;
+    for high in [1usize << 38, 1 << 40, 1 << 63] {
+        assert_eq!(-1, mmap(start | high, PAGE, prot));
+        assert_eq!(-1, munmap(start | high, PAGE));
+    }
+    assert_eq!(0, mmap(start, PAGE, prot));
+    // 不能通过别名地址解除映射
+    assert_eq!(-1, munmap(start | 1 << 40, PAGE));
+    unsafe {
+        (start as *mut usize).write_volatile(1);
+    }
+    assert_eq!(0, munmap(start, PAGE));
+    println!("Test mmap canonical OK!");
+    0
+}
@@ -0,0 +1,35 @@
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user_lib;
+
+use user_lib::{mmap, munmap};
+
+/*
+理想结果：可执行的页总是同时可读，port 为 0b100、0b101、0b110 时 mmap 成功且页可读，
+0b110 的页还可写，输出 Test mmap exec OK!
+*/
+
+const PAGE: usize = 4096;
+
+#[no_mangle]
+fn main() -> i32 {
+    let start: usize = 0x10000000;
+    for (i, port) in [0b100usize, 0b101, 0b110].iter().enumerate() {
+        let addr = start + i * PAGE;
+        assert_eq!(0, mmap(addr, PAGE, *port));
+        let ptr = addr as *mut usize;
+        unsafe {
+            // 新映射的页已清零
+            assert_eq!(0, ptr.read_volatile());
+            if port & 0b010 != 0 {
+                ptr.write_volatile(i + 1);
+                assert_eq!(i + 1, ptr.read_volatile());
+            }
+        }
+    }
+    assert_eq!(0, munmap(start, PAGE * 3));
+    println!("Test mmap exec OK!");
+    0
+}
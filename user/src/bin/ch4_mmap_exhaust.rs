@@ -0,0 +1,36 @@
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user_lib;
+
+use user_lib::{mmap, munmap};
+
+/*
+理想结果：超过物理内存大小的 mmap 返回 -1 而内核不 panic，且不占用任何页帧；
+之后同一位置的小块 mmap 仍然成功，输出 Test mmap exhaust OK!
+页帧真正耗尽时的回滚由内核自测 oom_rollback_test 检查，这里不去耗尽其他任务也要用的内存
+*/
+
+#[no_mangle]
+fn main() -> i32 {
+    let start: usize = 0x10000000;
+    let chunk: usize = 4096 * 64;
+    let prot: usize = 3;
+    // 256MiB 远超 128MiB 的物理内存，在分配任何页帧之前就被拒绝
+    let oversized: usize = 256 << 20;
+    assert_eq!(-1, mmap(start, oversized, prot));
+    assert_eq!(-1, mmap(start + chunk, oversized, prot));
+    // 被拒绝的请求没有留下映射
+    assert_eq!(-1, munmap(start, chunk));
+    assert_eq!(0, mmap(start, chunk, prot));
+    for page in 0..chunk / 4096 {
+        let addr = (start + page * 4096) as *mut usize;
+        unsafe {
+            addr.write_volatile(page);
+        }
+    }
+    assert_eq!(0, munmap(start, chunk));
+    println!("Test mmap exhaust OK!");
+    0
+}
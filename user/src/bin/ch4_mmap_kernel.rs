@@ -0,0 +1,31 @@
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user_lib;
+
+use user_lib::{mmap, munmap, sbrk};
+
+/*
+理想结果：映射或解除映射跳板页、Trap 上下文页以及用户栈下方的保护页都返回 -1，输出 Test mmap kernel OK!
+*/
+
+#[no_mangle]
+fn main() -> i32 {
+    let len: usize = 4096;
+    let prot: usize = 3;
+    let trampoline: usize = usize::MAX - len + 1;
+    let trap_context: usize = trampoline - len;
+    assert_eq!(-1, mmap(trampoline, len, prot));
+    assert_eq!(-1, mmap(trap_context, len, prot));
+    assert_eq!(-1, mmap(trap_context, len * 2, prot));
+    assert_eq!(-1, munmap(trampoline, len));
+    assert_eq!(-1, munmap(trap_context, len));
+    // 堆紧挨在用户栈（两页）之上，保护页在用户栈之下
+    let user_stack_top = sbrk(0) as usize;
+    let guard_page = user_stack_top - len * 2 - len;
+    assert_eq!(-1, mmap(guard_page, len, prot));
+    assert_eq!(-1, munmap(guard_page, len));
+    println!("Test mmap kernel OK!");
+    0
+}
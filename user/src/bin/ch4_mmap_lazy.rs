@@ -0,0 +1,39 @@
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user_lib;
+
+use user_lib::{mmap, munmap, MMAP_LAZY};
+
+/*
+理想结果：按需分配的 mmap 区间在首次访问时才映射，且可以正常读写和解除映射，输出 Test mmap lazy OK!
+*/
+
+#[no_mangle]
+fn main() -> i32 {
+    let start: usize = 0x10000000;
+    let len: usize = 4096 * 16;
+    let prot: usize = 3;
+    assert_eq!(0, mmap(start, len, prot | MMAP_LAZY));
+    // 尚未访问的页同样不能被重复映射
+    assert_eq!(-1, mmap(start + 4096 * 8, 4096, prot));
+    for addr in [start, start + 4096 * 7 + 5, start + len - 1] {
+        unsafe {
+            (addr as *mut u8).write_volatile(addr as u8);
+        }
+    }
+    for addr in [start, start + 4096 * 7 + 5, start + len - 1] {
+        unsafe {
+            assert_eq!((addr as *const u8).read_volatile(), addr as u8);
+        }
+    }
+    // 未访问过的页读出为 0
+    unsafe {
+        assert_eq!(((start + 4096 * 3) as *const u8).read_volatile(), 0);
+    }
+    assert_eq!(0, munmap(start, len));
+    assert_eq!(0, mmap(start, 4096, prot));
+    println!("Test mmap lazy OK!");
+    0
+}
@@ -0,0 +1,28 @@
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user_lib;
+
+use user_lib::{mmap, munmap, shmat, shmget};
+
+/*
+理想结果：第 0 页不会被 mmap 或 shmat 映射，mmap(0, ..) 由内核另选地址，
+shmat 到地址 0 返回 -1，输出 Test mmap null page OK!
+*/
+
+const PAGE: usize = 4096;
+
+#[no_mangle]
+fn main() -> i32 {
+    let prot_read: usize = 1;
+    let addr = mmap(0, PAGE, prot_read);
+    assert!(addr > 0);
+    assert!(addr as usize >= PAGE);
+    assert_eq!(0, munmap(addr as usize, PAGE));
+    let key: usize = 0x4e55;
+    assert_eq!(0, shmget(key, PAGE));
+    assert_eq!(-1, shmat(key, 0));
+    println!("Test mmap null page OK!");
+    0
+}
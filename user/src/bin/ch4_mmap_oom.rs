@@ -0,0 +1,36 @@
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user_lib;
+
+use user_lib::{mmap, munmap};
+
+/*
+理想结果：物理页不足时 mmap 返回 -1 且内核不 panic，已分配的页全部回收，之后的 mmap 仍能成功，
+输出 Test mmap oom OK!
+*/
+
+#[no_mangle]
+fn main() -> i32 {
+    let start: usize = 0x10000000;
+    let len: usize = 4096;
+    let prot: usize = 3;
+    // 1GiB 远超物理内存
+    assert_eq!(-1, mmap(start, 1 << 30, prot));
+    // 失败的 mmap 不留下任何映射
+    assert_eq!(-1, munmap(start, len));
+    for _ in 0..4 {
+        assert_eq!(-1, mmap(start, 1 << 30, prot));
+    }
+    assert_eq!(0, mmap(start, len * 16, prot));
+    for i in start..(start + len * 16) {
+        let addr: *mut u8 = i as *mut u8;
+        unsafe {
+            *addr = i as u8;
+        }
+    }
+    assert_eq!(0, munmap(start, len * 16));
+    println!("Test mmap oom OK!");
+    0
+}
@@ -0,0 +1,35 @@
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user_lib;
+
+use user_lib::{mmap, munmap};
+
+/*
+理想结果：start + len 溢出或超出用户地址空间（1 << 38）时 mmap 返回 -1，输出 Test mmap overflow OK!
+*/
+
+#[no_mangle]
+fn main() -> i32 {
+    let start: usize = 0x10000000;
+    let len: usize = 4096;
+    let prot: usize = 3;
+    let user_space_end: usize = 1 << 38;
+    assert_eq!(-1, mmap(start, usize::MAX, prot));
+    assert_eq!(-1, mmap(usize::MAX - len + 1, len, prot));
+    assert_eq!(-1, munmap(start, usize::MAX));
+    // 用户地址空间的最后一页可以映射，但不能越过其末尾
+    let last_page = user_space_end - len;
+    assert_eq!(-1, mmap(last_page, len * 2, prot));
+    assert_eq!(-1, mmap(user_space_end, len, prot));
+    assert_eq!(0, mmap(last_page, len, prot));
+    let addr: *mut u8 = last_page as *mut u8;
+    unsafe {
+        *addr = 42;
+        assert_eq!(*addr, 42);
+    }
+    assert_eq!(0, munmap(last_page, len));
+    println!("Test mmap overflow OK!");
+    0
+}
@@ -0,0 +1,37 @@
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user_lib;
+
+use user_lib::mmap;
+
+/*
+理想结果：重叠的 mmap 返回 -1 且不映射任何页，输出 Test mmap overlap OK!
+*/
+
+#[no_mangle]
+fn main() -> i32 {
+    let start: usize = 0x10000000;
+    let len: usize = 4096;
+    let prot: usize = 3;
+    assert_eq!(0, mmap(start, len * 2, prot));
+    assert_eq!(-1, mmap(start, len * 2, prot));
+    // 部分重叠：后半段 [start + 2 * len, start + 3 * len) 也不应被映射
+    assert_eq!(-1, mmap(start + len, len * 2, prot));
+    assert_eq!(0, mmap(start + len * 2, len, prot));
+    for i in start..(start + len * 3) {
+        let addr: *mut u8 = i as *mut u8;
+        unsafe {
+            *addr = i as u8;
+        }
+    }
+    for i in start..(start + len * 3) {
+        let addr: *mut u8 = i as *mut u8;
+        unsafe {
+            assert_eq!(*addr, i as u8);
+        }
+    }
+    println!("Test mmap overlap OK!");
+    0
+}
@@ -0,0 +1,34 @@
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user_lib;
+
+use user_lib::{mmap, munmap};
+
+/*
+理想结果：len 不是页大小的整数倍时向上取整，mmap PAGE + 1 字节会映射两页，
+两页都可读写，第二页可以单独 munmap，输出 Test mmap partial page OK!
+*/
+
+const PAGE: usize = 4096;
+
+#[no_mangle]
+fn main() -> i32 {
+    let start: usize = 0x10000000;
+    let prot: usize = 3;
+    assert_eq!(0, mmap(start, PAGE + 1, prot));
+    for page in 0..2 {
+        let addr = (start + page * PAGE) as *mut usize;
+        unsafe {
+            addr.write_volatile(page + 1);
+            assert_eq!(page + 1, addr.read_volatile());
+        }
+    }
+    // 第三页不在映射范围内
+    assert_eq!(-1, munmap(start + PAGE * 2, PAGE));
+    assert_eq!(0, munmap(start + PAGE, PAGE));
+    assert_eq!(0, munmap(start, PAGE));
+    println!("Test mmap partial page OK!");
+    0
+}
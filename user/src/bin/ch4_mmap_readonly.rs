@@ -0,0 +1,27 @@
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user_lib;
+
+use user_lib::mmap;
+
+/*
+理想结果：只读映射的页可以读取，写入时程序被内核杀死（输出 PageFault），不会输出 FAIL
+*/
+
+#[no_mangle]
+fn main() -> i32 {
+    let start: usize = 0x10000000;
+    let len: usize = 4096;
+    let prot: usize = 1;
+    assert_eq!(0, mmap(start, len, prot));
+    let addr: *mut u8 = start as *mut u8;
+    unsafe {
+        assert_eq!(0, addr.read_volatile());
+        println!("Test read-only mmap: writing to a read-only page");
+        addr.write_volatile(1);
+    }
+    println!("FAIL: T.T");
+    0
+}
@@ -0,0 +1,35 @@
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user_lib;
+
+use user_lib::{mmap, munmap};
+
+/*
+理想结果：四页的 mmap 在第三页与已有映射冲突时返回 -1，前两页也不会被映射，
+之后可以重新映射这两页，输出 Test mmap rollback OK!
+*/
+
+#[no_mangle]
+fn main() -> i32 {
+    let start: usize = 0x10000000;
+    let len: usize = 4096;
+    let prot: usize = 3;
+    assert_eq!(0, mmap(start + len * 2, len, prot));
+    assert_eq!(-1, mmap(start, len * 4, prot));
+    // 前两页没有残留的映射
+    assert_eq!(-1, munmap(start, len));
+    assert_eq!(-1, munmap(start + len, len));
+    assert_eq!(0, mmap(start, len * 2, prot));
+    for i in start..(start + len * 3) {
+        let addr: *mut u8 = i as *mut u8;
+        unsafe {
+            *addr = i as u8;
+        }
+    }
+    assert_eq!(0, munmap(start, len * 2));
+    assert_eq!(0, munmap(start + len * 2, len));
+    println!("Test mmap rollback OK!");
+    0
+}
@@ -0,0 +1,57 @@
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user_lib;
+
+use user_lib::{exit, fork, mmap, munmap, wait, MMAP_LAZY};
+
+/*
+理想结果：munmap 后重新 mmap 得到的页全部为 0，不论旧数据来自本进程还是已退出的子进程，
+输出 Test mmap zeroed OK!
+*/
+
+const PATTERN: u8 = 0x5a;
+
+fn fill(start: usize, len: usize) {
+    for addr in start..start + len {
+        unsafe { (addr as *mut u8).write_volatile(PATTERN) };
+    }
+}
+
+fn all_zero(start: usize, len: usize) -> bool {
+    (start..start + len).all(|addr| unsafe { (addr as *const u8).read_volatile() } == 0)
+}
+
+#[no_mangle]
+fn main() -> i32 {
+    let start: usize = 0x10000000;
+    let len: usize = 4096 * 4;
+    let prot: usize = 3;
+    assert_eq!(0, mmap(start, len, prot));
+    fill(start, len);
+    assert_eq!(0, munmap(start, len));
+    // 刚释放的页框最先被重新分配
+    assert_eq!(0, mmap(start, len, prot));
+    assert!(all_zero(start, len));
+    assert_eq!(0, munmap(start, len));
+    assert_eq!(0, mmap(start, len, prot | MMAP_LAZY));
+    fill(start, len);
+    assert_eq!(0, munmap(start, len));
+    assert_eq!(0, mmap(start, len, prot | MMAP_LAZY));
+    assert!(all_zero(start, len));
+    assert_eq!(0, munmap(start, len));
+    // 子进程退出后留下的页框
+    if fork() == 0 {
+        assert_eq!(0, mmap(start, len, prot));
+        fill(start, len);
+        exit(0);
+    }
+    let mut exit_code: i32 = -1;
+    assert!(wait(&mut exit_code) > 0);
+    assert_eq!(exit_code, 0);
+    assert_eq!(0, mmap(start, len, prot));
+    assert!(all_zero(start, len));
+    println!("Test mmap zeroed OK!");
+    0
+}
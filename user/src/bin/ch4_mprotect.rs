@@ -0,0 +1,41 @@
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user_lib;
+
+use user_lib::{mmap, mprotect};
+
+/*
+理想结果：先写入代码再通过 mprotect 改为可执行并成功调用，输出 Test mprotect OK!
+*/
+
+#[no_mangle]
+fn main() -> i32 {
+    let start: usize = 0x10000000;
+    let len: usize = 4096;
+    assert_eq!(0, mmap(start, len, 3));
+    // li a0, 42; ret
+    let code: [u32; 2] = [0x02a00513, 0x00008067];
+    for (i, inst) in code.iter().enumerate() {
+        unsafe {
+            ((start + i * 4) as *mut u32).write_volatile(*inst);
+        }
+    }
+    // 非法参数与未映射区间
+    assert_eq!(-1, mprotect(start + 1, len, 5));
+    assert_eq!(-1, mprotect(start, len, 0));
+    assert_eq!(-1, mprotect(start, len, 8));
+    assert_eq!(-1, mprotect(start, len * 2, 5));
+    assert_eq!(0, mprotect(start, len, 5));
+    unsafe {
+        core::arch::asm!("fence.i");
+    }
+    let f: extern "C" fn() -> usize = unsafe { core::mem::transmute(start) };
+    assert_eq!(42, f());
+    // 改为只读后仍可读取原有数据
+    assert_eq!(0, mprotect(start, len, 1));
+    assert_eq!(code[0], unsafe { (start as *const u32).read_volatile() });
+    println!("Test mprotect OK!");
+    0
+}
@@ -0,0 +1,50 @@
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user_lib;
+
+use user_lib::{get_pte_flags, mmap, mprotect, munmap};
+
+/*
+理想结果：内核以 test-syscalls 特性编译时（make run FEATURES=test-syscalls），
+mprotect 前后读到的页表项权限分别为 RW 与 R，解除映射后返回 -1，
+输出 Test mprotect flags OK!
+未启用该特性时查询已映射的页也返回 -1，输出 Test mprotect flags skipped! 并正常退出
+*/
+
+const PAGE: usize = 4096;
+const V: isize = 1 << 0;
+const R: isize = 1 << 1;
+const W: isize = 1 << 2;
+const X: isize = 1 << 3;
+const U: isize = 1 << 4;
+const PERM_MASK: isize = V | R | W | X | U;
+
+#[no_mangle]
+fn main() -> i32 {
+    let start: usize = 0x10000000;
+    assert_eq!(-1, get_pte_flags(start));
+    assert_eq!(0, mmap(start, PAGE * 2, 3));
+    if get_pte_flags(start) == -1 {
+        // 内核没有提供 sys_get_pte_flags
+        assert_eq!(0, munmap(start, PAGE * 2));
+        println!("Test mprotect flags skipped!");
+        return 0;
+    }
+    assert_eq!(V | R | W | U, get_pte_flags(start) & PERM_MASK);
+    // 页内任意地址读到的都是同一个页表项
+    assert_eq!(V | R | W | U, get_pte_flags(start + PAGE + 8) & PERM_MASK);
+    assert_eq!(0, mprotect(start, PAGE, 1));
+    assert_eq!(V | R | U, get_pte_flags(start) & PERM_MASK);
+    assert_eq!(V | R | W | U, get_pte_flags(start + PAGE) & PERM_MASK);
+    assert_eq!(0, mprotect(start, PAGE * 2, 5));
+    assert_eq!(V | R | X | U, get_pte_flags(start) & PERM_MASK);
+    assert_eq!(V | R | X | U, get_pte_flags(start + PAGE) & PERM_MASK);
+    assert_eq!(0, munmap(start, PAGE * 2));
+    assert_eq!(-1, get_pte_flags(start));
+    // 内核地址不可查询
+    assert_eq!(-1, get_pte_flags(0x80200000));
+    println!("Test mprotect flags OK!");
+    0
+}
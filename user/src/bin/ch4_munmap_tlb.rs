@@ -0,0 +1,41 @@
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user_lib;
+
+use user_lib::{exit, fork, mmap, munmap, waitpid};
+
+/*
+理想结果：刚访问过的页 munmap 之后不能再通过 TLB 中的旧表项访问，
+读取会使进程因缺页被杀死，输出 Test munmap tlb OK!
+*/
+
+const PAGE: usize = 4096;
+
+#[no_mangle]
+fn main() -> i32 {
+    let start: usize = 0x10000000;
+    let prot: usize = 3;
+    let pid = fork();
+    if pid == 0 {
+        assert_eq!(0, mmap(start, PAGE, prot));
+        let addr = start as *mut usize;
+        unsafe {
+            // 读写一次使该页进入 TLB
+            addr.write_volatile(0x5a5a);
+            assert_eq!(0x5a5a, addr.read_volatile());
+        }
+        assert_eq!(0, munmap(start, PAGE));
+        unsafe {
+            addr.read_volatile();
+        }
+        println!("Read an unmapped page, Test munmap tlb fail!");
+        exit(0);
+    }
+    let mut exit_code: i32 = 0;
+    assert_eq!(pid, waitpid(pid as usize, &mut exit_code));
+    assert_eq!(exit_code, -2);
+    println!("Test munmap tlb OK!");
+    0
+}
@@ -0,0 +1,38 @@
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user_lib;
+
+use user_lib::{get_time, mmap, sys_nanosleep, TimeVal};
+
+/*
+理想结果：nanosleep 50ms 返回时至少经过了 50ms，TimeVal 跨越页边界时也能读出，
+usec 不合法或指针不可读时返回 -1，输出 Test nanosleep OK!
+*/
+
+#[no_mangle]
+fn main() -> i32 {
+    let start = get_time();
+    let req = TimeVal {
+        sec: 0,
+        usec: 50_000,
+    };
+    assert_eq!(0, sys_nanosleep(&req));
+    assert!(get_time() - start >= 50);
+    // sec 落在第一页末尾，usec 落在第二页开头
+    let base: usize = 0x10000000;
+    assert_eq!(0, mmap(base, 4096 * 2, 3));
+    let addr = base + 4096 - core::mem::size_of::<usize>();
+    let req = unsafe { &mut *(addr as *mut TimeVal) };
+    req.sec = 0;
+    req.usec = 20_000;
+    let start = get_time();
+    assert_eq!(0, sys_nanosleep(req));
+    assert!(get_time() - start >= 20);
+    req.usec = 1_000_000;
+    assert_eq!(-1, sys_nanosleep(req));
+    assert_eq!(-1, sys_nanosleep(unsafe { &*(0x20000000 as *const TimeVal) }));
+    println!("Test nanosleep OK!");
+    0
+}
@@ -0,0 +1,32 @@
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user_lib;
+
+use user_lib::{mmap, munmap, page_status};
+
+/*
+理想结果：读过的页 accessed 置位，写过的页 accessed 与 dirty 都置位，未访问的页为 0，
+区间内有未映射的页时返回 -1，输出 Test page status OK!
+*/
+
+#[no_mangle]
+fn main() -> i32 {
+    let start: usize = 0x10000000;
+    let len: usize = 4096;
+    let prot: usize = 3;
+    let mut status = [0xffu8; 3];
+    assert_eq!(0, mmap(start, len * 3, prot));
+    unsafe {
+        assert_eq!(0, (start as *const u8).read_volatile());
+        ((start + len) as *mut u8).write_volatile(1);
+    }
+    assert_eq!(0, page_status(start, len * 3, &mut status));
+    assert_eq!(status, [0b01, 0b11, 0b00]);
+    assert_eq!(-1, page_status(start, len * 4, &mut status));
+    assert_eq!(-1, page_status(start + 1, len, &mut status));
+    assert_eq!(0, munmap(start, len * 3));
+    println!("Test page status OK!");
+    0
+}
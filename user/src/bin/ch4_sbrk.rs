@@ -0,0 +1,40 @@
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user_lib;
+
+use user_lib::sbrk;
+
+/*
+理想结果：堆可以增长、读写并收缩，收缩到堆底以下返回 -1，输出 Test sbrk OK!
+*/
+
+#[no_mangle]
+fn main() -> i32 {
+    let len: usize = 4096;
+    let heap_bottom = sbrk(0);
+    assert!(heap_bottom > 0);
+    let heap_bottom = heap_bottom as usize;
+    // 增长两页并写满
+    assert_eq!(heap_bottom as isize, sbrk((len * 2) as i32));
+    for i in heap_bottom..(heap_bottom + len * 2) {
+        let addr: *mut u8 = i as *mut u8;
+        unsafe {
+            *addr = i as u8;
+        }
+    }
+    // 收缩一页，剩下的一页保持不变
+    assert_eq!((heap_bottom + len * 2) as isize, sbrk(-(len as i32)));
+    for i in heap_bottom..(heap_bottom + len) {
+        let addr: *mut u8 = i as *mut u8;
+        unsafe {
+            assert_eq!(*addr, i as u8);
+        }
+    }
+    assert_eq!(-1, sbrk(-((len * 2) as i32)));
+    assert_eq!((heap_bottom + len) as isize, sbrk(-(len as i32)));
+    assert_eq!(heap_bottom as isize, sbrk(0));
+    println!("Test sbrk OK!");
+    0
+}
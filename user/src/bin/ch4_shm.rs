@@ -0,0 +1,47 @@
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user_lib;
+
+use user_lib::{mmap, munmap, shmat, shmget};
+
+/*
+理想结果：同一共享内存段映射到两个地址后，从一处写入的数据在另一处可见，输出 Test shm OK!
+*/
+
+#[no_mangle]
+fn main() -> i32 {
+    let key: usize = 42;
+    let first: usize = 0x10000000;
+    let second: usize = 0x20000000;
+    let len: usize = 4096 * 2;
+    assert_eq!(-1, shmat(key, first));
+    assert_eq!(-1, shmget(key, 0));
+    assert_eq!(0, shmget(key, len));
+    // 已存在的段不能比请求的小
+    assert_eq!(0, shmget(key, len - 1));
+    assert_eq!(-1, shmget(key, len + 4096));
+    assert_eq!(-1, shmat(key, first + 1));
+    assert_eq!(0, shmat(key, first));
+    assert_eq!(0, shmat(key, second));
+    for i in 0..len {
+        let addr: *mut u8 = (first + i) as *mut u8;
+        unsafe {
+            *addr = i as u8;
+        }
+    }
+    for i in 0..len {
+        let addr: *const u8 = (second + i) as *const u8;
+        unsafe {
+            assert_eq!(*addr, i as u8);
+        }
+    }
+    // 与已有映射重叠
+    assert_eq!(-1, shmat(key, first + 4096));
+    assert_eq!(-1, mmap(second, 4096, 3));
+    assert_eq!(0, munmap(first, len));
+    assert_eq!(0, munmap(second, len));
+    println!("Test shm OK!");
+    0
+}
@@ -0,0 +1,43 @@
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user_lib;
+
+use user_lib::{exit, fork, shmat, shmget, wait};
+
+/*
+理想结果：fork 后共享内存段不会写时复制，父子进程写入的数据彼此可见，输出 Test shm fork OK!
+*/
+
+#[no_mangle]
+fn main() -> i32 {
+    let key: usize = 43;
+    let start: usize = 0x10000000;
+    let len: usize = 4096 * 2;
+    assert_eq!(0, shmget(key, len));
+    assert_eq!(0, shmat(key, start));
+    let flag = start as *mut u8;
+    let data = (start + 4096) as *mut u8;
+    unsafe {
+        flag.write_volatile(1);
+    }
+    if fork() == 0 {
+        // 子进程读到 fork 前写入的数据，并写回给父进程
+        unsafe {
+            assert_eq!(flag.read_volatile(), 1);
+            data.write_volatile(0x5a);
+            flag.write_volatile(2);
+        }
+        exit(0);
+    }
+    let mut exit_code: i32 = -1;
+    assert!(wait(&mut exit_code) > 0);
+    assert_eq!(exit_code, 0);
+    unsafe {
+        assert_eq!(flag.read_volatile(), 2);
+        assert_eq!(data.read_volatile(), 0x5a);
+    }
+    println!("Test shm fork OK!");
+    0
+}
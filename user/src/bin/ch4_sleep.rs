@@ -0,0 +1,35 @@
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user_lib;
+
+use user_lib::{exit, fork, get_time, sys_sleep, wait};
+
+/*
+理想结果：sleep 50ms 返回时至少经过了 50ms；多个子进程同时睡眠不同的时间，
+按睡眠时间由短到长的顺序退出，输出 Test sleep syscall OK!
+*/
+
+#[no_mangle]
+fn main() -> i32 {
+    let start = get_time();
+    assert_eq!(0, sys_sleep(50));
+    assert!(get_time() - start >= 50);
+    // 子进程 i 睡眠 (3 - i) * 40ms，因此最后创建的最先醒来
+    for i in 0..3 {
+        if fork() == 0 {
+            let start = get_time();
+            sys_sleep((3 - i) * 40);
+            assert!(get_time() - start >= ((3 - i) * 40) as isize);
+            exit(i as i32);
+        }
+    }
+    let mut exit_code: i32 = -1;
+    for i in (0..3).rev() {
+        assert!(wait(&mut exit_code) > 0);
+        assert_eq!(exit_code, i);
+    }
+    println!("Test sleep syscall OK!");
+    0
+}
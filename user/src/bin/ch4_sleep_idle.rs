@@ -0,0 +1,37 @@
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user_lib;
+
+use user_lib::{exit, fork, get_time, sys_sleep, task_info, wait, TaskInfo, TaskStatus};
+
+/*
+理想结果：所有任务同时睡眠时内核等待最早的任务醒来，各任务都睡够指定时间，
+醒来后 task_info 报告的状态为 Running，输出 Test sleep idle OK!
+*/
+
+#[no_mangle]
+fn main() -> i32 {
+    if fork() == 0 {
+        let start = get_time();
+        assert_eq!(0, sys_sleep(30));
+        assert!(get_time() - start >= 30);
+        let info = TaskInfo::new();
+        assert_eq!(0, task_info(&info));
+        assert!(info.status == TaskStatus::Running);
+        exit(0);
+    }
+    // 子进程睡眠期间父进程也在睡眠，没有可运行的任务
+    let start = get_time();
+    assert_eq!(0, sys_sleep(60));
+    assert!(get_time() - start >= 60);
+    let info = TaskInfo::new();
+    assert_eq!(0, task_info(&info));
+    assert!(info.status == TaskStatus::Running);
+    let mut exit_code: i32 = -1;
+    assert!(wait(&mut exit_code) > 0);
+    assert_eq!(exit_code, 0);
+    println!("Test sleep idle OK!");
+    0
+}
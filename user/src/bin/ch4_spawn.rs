@@ -0,0 +1,33 @@
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user_lib;
+
+use user_lib::{getpid, mmap, spawn, waitpid};
+
+/*
+理想结果：不存在的程序 spawn 返回 -1；spawn 出的子进程独立运行 ch4_exec_target，
+当前进程的地址空间不受影响，waitpid 回收到子进程的退出码 0，
+输出 Test exec target OK! 与 Test spawn OK!
+*/
+
+#[no_mangle]
+fn main() -> i32 {
+    assert_eq!(-1, spawn("ch4_no_such_app\0"));
+    let start: usize = 0x10000000;
+    assert_eq!(0, mmap(start, 4096, 3));
+    unsafe {
+        (start as *mut u8).write_volatile(42);
+    }
+    let pid = spawn("ch4_exec_target\0");
+    assert!(pid > 0 && pid != getpid());
+    unsafe {
+        assert_eq!(42, (start as *const u8).read_volatile());
+    }
+    let mut exit_code: i32 = -1;
+    assert_eq!(pid, waitpid(pid as usize, &mut exit_code));
+    assert_eq!(exit_code, 0);
+    println!("Test spawn OK!");
+    0
+}
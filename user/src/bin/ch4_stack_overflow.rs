@@ -0,0 +1,28 @@
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user_lib;
+
+/*
+理想结果：递归耗尽用户栈后访问栈下方的保护页，内核报告 Stack overflow 并只杀死该程序，
+不会输出 FAIL，后续程序正常运行
+*/
+
+#[allow(unconditional_recursion)]
+fn recurse(depth: usize) -> usize {
+    let mut frame = [0u8; 512];
+    // keep the frame alive so the recursion can't be turned into a loop
+    unsafe {
+        (&mut frame[0] as *mut u8).write_volatile(depth as u8);
+    }
+    recurse(depth + 1) + frame[0] as usize
+}
+
+#[no_mangle]
+pub fn main() -> i32 {
+    println!("Test stack overflow: recursing until the guard page is hit");
+    recurse(0);
+    println!("FAIL: T.T");
+    0
+}
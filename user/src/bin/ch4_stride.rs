@@ -0,0 +1,58 @@
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user_lib;
+
+use user_lib::{exit, fork, get_time, set_priority, shmat, shmget, waitpid};
+
+/*
+理想结果：优先级非法时 set_priority 返回 -1；优先级为 4 的父进程与优先级为 2 的子进程
+同时忙循环相同的时间，父进程的计数约为子进程的两倍，输出 Test stride OK!
+*/
+
+fn spin_until(deadline: isize) -> usize {
+    let mut count: usize = 0;
+    let counter = &mut count as *mut usize;
+    while get_time() < deadline {
+        // 防止循环被优化掉
+        for _ in 0..1000 {
+            unsafe {
+                counter.write_volatile(counter.read_volatile() + 1);
+            }
+        }
+    }
+    count
+}
+
+#[no_mangle]
+fn main() -> i32 {
+    assert_eq!(-1, set_priority(1));
+    assert_eq!(-1, set_priority(-3));
+    let key: usize = 4096;
+    let shared: usize = 0x10000000;
+    assert_eq!(0, shmget(key, 4096));
+    let deadline = get_time() + 1000;
+    let pid = fork();
+    assert_eq!(0, shmat(key, shared));
+    let child_count = shared as *mut usize;
+    if pid == 0 {
+        assert_eq!(2, set_priority(2));
+        let count = spin_until(deadline);
+        unsafe {
+            child_count.write_volatile(count);
+        }
+        exit(0);
+    }
+    assert_eq!(4, set_priority(4));
+    let count = spin_until(deadline);
+    let mut exit_code: i32 = -1;
+    assert_eq!(pid, waitpid(pid as usize, &mut exit_code));
+    assert_eq!(0, exit_code);
+    let child_count = unsafe { child_count.read_volatile() };
+    println!("priority 4: {}, priority 2: {}", count, child_count);
+    // 允许一定误差，比值在 1.5 到 2.5 之间
+    assert!(count * 2 >= child_count * 3 && count * 2 <= child_count * 5);
+    println!("Test stride OK!");
+    0
+}
@@ -0,0 +1,26 @@
+#![no_std]
+#![no_main]
+
+extern crate user_lib;
+
+use user_lib::{println, task_info, yield_, TaskInfo, SYSCALL_YIELD};
+
+/*
+理想结果：syscall_times 正确统计 yield 次数，输出 Test syscall times OK!
+*/
+
+const N: u32 = 10;
+
+#[no_mangle]
+pub fn main() -> usize {
+    let info = TaskInfo::new();
+    assert_eq!(0, task_info(&info));
+    let before = info.syscall_times[SYSCALL_YIELD];
+    for _ in 0..N {
+        yield_();
+    }
+    assert_eq!(0, task_info(&info));
+    assert_eq!(before + N, info.syscall_times[SYSCALL_YIELD]);
+    println!("Test syscall times OK!");
+    0
+}
@@ -0,0 +1,26 @@
+#![no_std]
+#![no_main]
+
+extern crate user_lib;
+
+use user_lib::{
+    get_time, println, task_info, TaskInfo, TaskStatus, SYSCALL_GETTIMEOFDAY, SYSCALL_TASK_INFO,
+};
+
+/*
+理想结果：开启虚存后 task_info 仍能正确写回 TaskInfo，输出 Test ch4 task info OK!
+*/
+
+#[no_mangle]
+pub fn main() -> usize {
+    let t1 = get_time() as usize;
+    let info = TaskInfo::new();
+    assert_eq!(0, task_info(&info));
+    let t2 = get_time() as usize;
+    assert!(info.status == TaskStatus::Running);
+    assert_eq!(1, info.syscall_times[SYSCALL_TASK_INFO]);
+    assert!(1 <= info.syscall_times[SYSCALL_GETTIMEOFDAY]);
+    assert!(info.time < t2 - t1 + 100);
+    println!("Test ch4 task info OK!");
+    0
+}
@@ -0,0 +1,43 @@
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user_lib;
+
+use user_lib::{mmap, munmap, task_info, TaskInfo, MMAP_LAZY};
+
+/*
+理想结果：按需分配的区域只有被访问过的页计入常驻页数，mmap 10 页并访问其中 3 页后
+常驻页数增加 3，munmap 后恢复，输出 Test task info lazy OK!
+*/
+
+const PAGE: usize = 4096;
+
+#[no_mangle]
+fn main() -> i32 {
+    let start: usize = 0x10000000;
+    let prot: usize = 3;
+    let before = TaskInfo::new();
+    assert_eq!(0, task_info(&before));
+    assert_eq!(0, mmap(start, PAGE * 10, prot | MMAP_LAZY));
+    let info = TaskInfo::new();
+    assert_eq!(0, task_info(&info));
+    assert_eq!(before.resident_pages, info.resident_pages);
+    assert_eq!(before.area_count + 1, info.area_count);
+    for page in [0, 4, 9] {
+        unsafe {
+            ((start + page * PAGE) as *mut usize).write_volatile(page);
+        }
+    }
+    // 再次访问已分配的页不会增加常驻页数
+    unsafe {
+        ((start + 4 * PAGE + 8) as *mut usize).write_volatile(1);
+    }
+    assert_eq!(0, task_info(&info));
+    assert_eq!(before.resident_pages + 3, info.resident_pages);
+    assert_eq!(0, munmap(start, PAGE * 10));
+    assert_eq!(0, task_info(&info));
+    assert_eq!(before.resident_pages, info.resident_pages);
+    println!("Test task info lazy OK!");
+    0
+}
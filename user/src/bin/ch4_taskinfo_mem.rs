@@ -0,0 +1,42 @@
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user_lib;
+
+use user_lib::{mmap, munmap, task_info, TaskInfo, TaskStatus};
+
+/*
+理想结果：TaskInfo 跨越页边界时仍能完整写回，mmap/munmap 后常驻页数与区域数随之变化，
+输出 Test task info memory OK!
+*/
+
+#[no_mangle]
+fn main() -> i32 {
+    let start: usize = 0x10000000;
+    let len: usize = 4096;
+    let prot: usize = 3;
+    let before = TaskInfo::new();
+    assert_eq!(0, task_info(&before));
+    assert!(before.resident_pages > 0);
+    assert!(before.area_count > 0);
+    // TaskInfo 放在两页交界处，前半部分在第一页
+    assert_eq!(0, mmap(start, len * 2, prot));
+    let addr = start + len - core::mem::size_of::<TaskInfo>() / 2;
+    let info = unsafe { &mut *(addr as *mut TaskInfo) };
+    *info = TaskInfo::new();
+    assert_eq!(0, task_info(info));
+    assert!(info.status == TaskStatus::Running);
+    assert_eq!(before.resident_pages + 2, info.resident_pages);
+    assert_eq!(before.area_count + 1, info.area_count);
+    assert_eq!(0, mmap(start + len * 2, len * 3, prot));
+    assert_eq!(0, task_info(info));
+    assert_eq!(before.resident_pages + 5, info.resident_pages);
+    assert_eq!(before.area_count + 2, info.area_count);
+    assert_eq!(0, munmap(start + len * 2, len * 3));
+    assert_eq!(0, task_info(info));
+    assert_eq!(before.resident_pages + 2, info.resident_pages);
+    assert_eq!(before.area_count + 1, info.area_count);
+    println!("Test task info memory OK!");
+    0
+}
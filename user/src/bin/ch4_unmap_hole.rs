@@ -0,0 +1,35 @@
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user_lib;
+
+use user_lib::{mmap, munmap};
+
+/*
+理想结果：munmap 的区间中存在未映射的页时返回 -1 且不改变已有映射，输出 Test unmap hole OK!
+*/
+
+#[no_mangle]
+fn main() -> i32 {
+    let start: usize = 0x10000000;
+    let len: usize = 4096;
+    let prot: usize = 3;
+    assert_eq!(0, mmap(start, len * 4, prot));
+    for i in start..(start + len * 4) {
+        let addr: *mut u8 = i as *mut u8;
+        unsafe {
+            *addr = i as u8;
+        }
+    }
+    assert_eq!(munmap(start, len * 6), -1);
+    for i in start..(start + len * 4) {
+        let addr: *mut u8 = i as *mut u8;
+        unsafe {
+            assert_eq!(*addr, i as u8);
+        }
+    }
+    assert_eq!(munmap(start, len * 4), 0);
+    println!("Test unmap hole OK!");
+    0
+}
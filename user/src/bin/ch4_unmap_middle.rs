@@ -0,0 +1,56 @@
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user_lib;
+
+use user_lib::{exit, fork, mmap, munmap, waitpid};
+
+/*
+理想结果：mmap 16 页后 munmap 中间的第 6..10 页，两侧的页仍可读写，
+访问中间的页会使进程因缺页被杀死，输出 Test unmap middle OK!
+*/
+
+const PAGE: usize = 4096;
+
+#[no_mangle]
+fn main() -> i32 {
+    let start: usize = 0x10000000;
+    let prot: usize = 3;
+    assert_eq!(0, mmap(start, PAGE * 16, prot));
+    for page in 0..16 {
+        let addr = (start + page * PAGE) as *mut usize;
+        unsafe {
+            addr.write_volatile(page);
+        }
+    }
+    assert_eq!(0, munmap(start + PAGE * 6, PAGE * 4));
+    for page in (0..6).chain(10..16) {
+        let addr = (start + page * PAGE) as *mut usize;
+        unsafe {
+            assert_eq!(page, addr.read_volatile());
+            addr.write_volatile(page + 16);
+            assert_eq!(page + 16, addr.read_volatile());
+        }
+    }
+    // 中间的页已不再映射
+    assert_eq!(-1, munmap(start + PAGE * 5, PAGE * 2));
+    for page in 6..10 {
+        let pid = fork();
+        if pid == 0 {
+            let addr = (start + page * PAGE) as *const usize;
+            unsafe {
+                addr.read_volatile();
+            }
+            exit(0);
+        }
+        let mut exit_code: i32 = 0;
+        assert_eq!(pid, waitpid(pid as usize, &mut exit_code));
+        assert_eq!(exit_code, -2);
+    }
+    // 中间可以重新映射，之后三段区域可以一次解除映射
+    assert_eq!(0, mmap(start + PAGE * 6, PAGE * 4, prot));
+    assert_eq!(0, munmap(start, PAGE * 16));
+    println!("Test unmap middle OK!");
+    0
+}
@@ -0,0 +1,38 @@
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user_lib;
+
+use user_lib::{mmap, munmap};
+
+/*
+理想结果：munmap 区间的最后一页从未映射时返回 -1，且前面的页保持不变，输出 Test unmap tail OK!
+*/
+
+#[no_mangle]
+fn main() -> i32 {
+    let start: usize = 0x10000000;
+    let len: usize = 4096;
+    let prot: usize = 3;
+    assert_eq!(0, mmap(start, len, prot));
+    assert_eq!(0, mmap(start + len, len, prot));
+    for i in start..(start + len * 2) {
+        let addr: *mut u8 = i as *mut u8;
+        unsafe {
+            *addr = i as u8;
+        }
+    }
+    assert_eq!(-1, munmap(start, len * 3));
+    for i in start..(start + len * 2) {
+        let addr: *mut u8 = i as *mut u8;
+        unsafe {
+            assert_eq!(*addr, i as u8);
+        }
+    }
+    // 两个完整的区域可以一次解除映射
+    assert_eq!(0, munmap(start, len * 2));
+    assert_eq!(0, mmap(start, len * 2, prot));
+    println!("Test unmap tail OK!");
+    0
+}
@@ -0,0 +1,32 @@
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user_lib;
+
+use user_lib::{exit, fork, sys_waitpid, yield_};
+
+/*
+理想结果：子进程先于父进程的 waitpid 退出，父进程直接回收到其 pid 与退出码，
+再次等待返回 -1，输出 Test waitpid exited OK!
+*/
+
+#[no_mangle]
+fn main() -> i32 {
+    let pid = fork();
+    if pid == 0 {
+        exit(7);
+    }
+    // 让子进程先运行完
+    for _ in 0..10 {
+        yield_();
+    }
+    let mut exit_code: i32 = 0;
+    assert_eq!(sys_waitpid(pid, &mut exit_code as *mut _), pid);
+    assert_eq!(exit_code, 7);
+    // 子进程已被回收
+    assert_eq!(sys_waitpid(pid, &mut exit_code as *mut _), -1);
+    assert_eq!(sys_waitpid(-1, &mut exit_code as *mut _), -1);
+    println!("Test waitpid exited OK!");
+    0
+}
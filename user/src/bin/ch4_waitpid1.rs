@@ -0,0 +1,55 @@
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user_lib;
+
+use user_lib::{exit, fork, sys_waitpid, yield_};
+
+/*
+理想结果：子进程尚未退出时 waitpid 返回 -2，退出后等待任意子进程回收到其 pid 与退出码，
+不存在的子进程与不可写的 exit_code_ptr 返回 -1，exit_code_ptr 为空时只回收不写回，
+输出 Test waitpid running OK!
+*/
+
+#[no_mangle]
+fn main() -> i32 {
+    let pid = fork();
+    if pid == 0 {
+        for _ in 0..10 {
+            yield_();
+        }
+        exit(-5);
+    }
+    let mut exit_code: i32 = 0;
+    assert_eq!(sys_waitpid(pid, &mut exit_code as *mut _), -2);
+    assert_eq!(sys_waitpid(pid + 1, &mut exit_code as *mut _), -1);
+    assert_eq!(sys_waitpid(pid, 0x10000000 as *mut i32), -1);
+    let ret = loop {
+        match sys_waitpid(-1, &mut exit_code as *mut _) {
+            -2 => {
+                yield_();
+            }
+            ret => break ret,
+        }
+    };
+    assert_eq!(ret, pid);
+    assert_eq!(exit_code, -5);
+    // 不关心退出码时传入空指针
+    let pid = fork();
+    if pid == 0 {
+        exit(7);
+    }
+    let ret = loop {
+        match sys_waitpid(pid, core::ptr::null_mut()) {
+            -2 => {
+                yield_();
+            }
+            ret => break ret,
+        }
+    };
+    assert_eq!(ret, pid);
+    assert_eq!(sys_waitpid(pid, core::ptr::null_mut()), -1);
+    println!("Test waitpid running OK!");
+    0
+}
@@ -98,12 +98,36 @@ impl TimeVal {
     }
 }
 
+#[repr(C)]
+#[derive(Debug, Default)]
+pub struct TimeSpec {
+    pub sec: usize,
+    pub nsec: usize,
+}
+
+#[repr(C)]
+#[derive(Debug, Default)]
+pub struct TimeZone {
+    pub minuteswest: i32,
+    pub dsttime: i32,
+}
+
+#[repr(C)]
+#[derive(Debug, Default)]
+pub struct FrameStats {
+    pub total: usize,
+    pub used: usize,
+    pub free: usize,
+}
+
 #[derive(Copy, Clone, PartialEq, Debug)]
 pub enum TaskStatus {
     UnInit,
     Ready,
     Running,
     Exited,
+    Zombie,
+    Sleeping,
 }
 
 #[derive(Copy, Clone, Debug)]
@@ -119,6 +143,8 @@ pub struct TaskInfo {
     pub status: TaskStatus,
     pub syscall_times: [u32; MAX_SYSCALL_NUM],
     pub time: usize,
+    pub resident_pages: usize,
+    pub area_count: usize,
 }
 
 impl TaskInfo {
@@ -127,6 +153,8 @@ impl TaskInfo {
             status: TaskStatus::UnInit,
             syscall_times: [0; MAX_SYSCALL_NUM],
             time: 0,
+            resident_pages: 0,
+            area_count: 0,
         }
     }
 }
@@ -224,6 +252,13 @@ pub fn yield_() -> isize {
     sys_yield()
 }
 
+pub const CLOCK_REALTIME: usize = 0;
+pub const CLOCK_MONOTONIC: usize = 1;
+
+pub fn clock_gettime(clock_id: usize, time: &TimeSpec) -> isize {
+    sys_clock_gettime(clock_id, time)
+}
+
 pub fn get_time() -> isize {
     let time = TimeVal::new();
     match sys_get_time(&time, 0) {
@@ -284,6 +319,9 @@ pub fn sleep(period_ms: usize) {
         sys_yield();
     }
 }
+/// `prot` bit of mmap asking the kernel to allocate frames on first access
+pub const MMAP_LAZY: usize = 1 << 8;
+
 pub fn mmap(start: usize, len: usize, prot: usize) -> isize {
     sys_mmap(start, len, prot)
 }
@@ -292,6 +330,22 @@ pub fn munmap(start: usize, len: usize) -> isize {
     sys_munmap(start, len)
 }
 
+pub fn mprotect(start: usize, len: usize, prot: usize) -> isize {
+    sys_mprotect(start, len, prot)
+}
+
+pub fn shmget(key: usize, len: usize) -> isize {
+    sys_shmget(key, len)
+}
+
+pub fn shmat(key: usize, addr: usize) -> isize {
+    sys_shmat(key, addr)
+}
+
+pub fn sbrk(size: i32) -> isize {
+    sys_sbrk(size)
+}
+
 pub fn spawn(path: &str) -> isize {
     sys_spawn(path)
 }
@@ -307,6 +361,22 @@ pub fn task_info(info: &TaskInfo) -> isize {
     sys_task_info(info)
 }
 
+pub fn page_status(start: usize, len: usize, buf: &mut [u8]) -> isize {
+    sys_page_status(start, len, buf)
+}
+
+pub fn clear_refs(start: usize, len: usize) -> isize {
+    sys_clear_refs(start, len)
+}
+
+pub fn frame_stats(stats: &mut FrameStats) -> isize {
+    sys_frame_stats(stats)
+}
+
+pub fn get_pte_flags(va: usize) -> isize {
+    sys_get_pte_flags(va)
+}
+
 pub fn thread_create(entry: usize, arg: usize) -> isize {
     sys_thread_create(entry, arg)
 }
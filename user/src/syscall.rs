@@ -1,6 +1,6 @@
 use crate::TaskInfo;
 
-use super::{Stat, TimeVal};
+use super::{FrameStats, Stat, TimeSpec, TimeVal};
 
 pub const SYSCALL_OPENAT: usize = 56;
 pub const SYSCALL_CLOSE: usize = 57;
@@ -11,22 +11,32 @@ pub const SYSCALL_LINKAT: usize = 37;
 pub const SYSCALL_FSTAT: usize = 80;
 pub const SYSCALL_EXIT: usize = 93;
 pub const SYSCALL_SLEEP: usize = 101;
+pub const SYSCALL_CLOCK_GETTIME: usize = 113;
 pub const SYSCALL_YIELD: usize = 124;
 pub const SYSCALL_GETTIMEOFDAY: usize = 169;
 pub const SYSCALL_GETPID: usize = 172;
+pub const SYSCALL_SHMGET: usize = 194;
+pub const SYSCALL_SHMAT: usize = 196;
 pub const SYSCALL_GETTID: usize = 178;
 pub const SYSCALL_FORK: usize = 220;
 pub const SYSCALL_EXEC: usize = 221;
 pub const SYSCALL_WAITPID: usize = 260;
 pub const SYSCALL_SET_PRIORITY: usize = 140;
+pub const SYSCALL_SBRK: usize = 214;
 pub const SYSCALL_MUNMAP: usize = 215;
 pub const SYSCALL_MMAP: usize = 222;
+pub const SYSCALL_MPROTECT: usize = 226;
 pub const SYSCALL_SPAWN: usize = 400;
 pub const SYSCALL_MAIL_READ: usize = 401;
 pub const SYSCALL_MAIL_WRITE: usize = 402;
 pub const SYSCALL_DUP: usize = 24;
 pub const SYSCALL_PIPE: usize = 59;
 pub const SYSCALL_TASK_INFO: usize = 410;
+pub const SYSCALL_PAGE_STATUS: usize = 411;
+pub const SYSCALL_CLEAR_REFS: usize = 412;
+pub const SYSCALL_GET_PTE_FLAGS: usize = 413;
+pub const SYSCALL_FRAME_STATS: usize = 414;
+pub const SYSCALL_NANOSLEEP: usize = 415;
 pub const SYSCALL_THREAD_CREATE: usize = 460;
 pub const SYSCALL_WAITTID: usize = 462;
 pub const SYSCALL_MUTEX_CREATE: usize = 463;
@@ -158,6 +168,10 @@ pub fn sys_get_time(time: &TimeVal, tz: usize) -> isize {
     syscall(SYSCALL_GETTIMEOFDAY, [time as *const _ as usize, tz, 0])
 }
 
+pub fn sys_clock_gettime(clock_id: usize, time: &TimeSpec) -> isize {
+    syscall(SYSCALL_CLOCK_GETTIME, [clock_id, time as *const _ as usize, 0])
+}
+
 pub fn sys_getpid() -> isize {
     syscall(SYSCALL_GETPID, [0, 0, 0])
 }
@@ -189,6 +203,22 @@ pub fn sys_munmap(start: usize, len: usize) -> isize {
     syscall(SYSCALL_MUNMAP, [start, len, 0])
 }
 
+pub fn sys_mprotect(start: usize, len: usize, prot: usize) -> isize {
+    syscall(SYSCALL_MPROTECT, [start, len, prot])
+}
+
+pub fn sys_shmget(key: usize, len: usize) -> isize {
+    syscall(SYSCALL_SHMGET, [key, len, 0])
+}
+
+pub fn sys_shmat(key: usize, addr: usize) -> isize {
+    syscall(SYSCALL_SHMAT, [key, addr, 0])
+}
+
+pub fn sys_sbrk(size: i32) -> isize {
+    syscall(SYSCALL_SBRK, [size as usize, 0, 0])
+}
+
 pub fn sys_spawn(path: &str) -> isize {
     syscall(SYSCALL_SPAWN, [path.as_ptr() as usize, 0, 0])
 }
@@ -205,6 +235,26 @@ pub fn sys_task_info(info: &TaskInfo) -> isize {
     syscall(SYSCALL_TASK_INFO, [info as *const _ as usize, 0, 0])
 }
 
+pub fn sys_page_status(start: usize, len: usize, buf: &mut [u8]) -> isize {
+    syscall(SYSCALL_PAGE_STATUS, [start, len, buf.as_mut_ptr() as usize])
+}
+
+pub fn sys_clear_refs(start: usize, len: usize) -> isize {
+    syscall(SYSCALL_CLEAR_REFS, [start, len, 0])
+}
+
+pub fn sys_frame_stats(stats: &mut FrameStats) -> isize {
+    syscall(SYSCALL_FRAME_STATS, [stats as *mut FrameStats as usize, 0, 0])
+}
+
+pub fn sys_nanosleep(req: &TimeVal) -> isize {
+    syscall(SYSCALL_NANOSLEEP, [req as *const TimeVal as usize, 0, 0])
+}
+
+pub fn sys_get_pte_flags(va: usize) -> isize {
+    syscall(SYSCALL_GET_PTE_FLAGS, [va, 0, 0])
+}
+
 pub fn sys_thread_create(entry: usize, arg: usize) -> isize {
     syscall(SYSCALL_THREAD_CREATE, [entry, arg, 0])
 }